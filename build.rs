@@ -0,0 +1,21 @@
+use std::{env, fs, path::Path};
+
+/// Embed the rule files of `cool_rules/` as presets, see `src/cool_rules.rs`.
+fn main() {
+    println!("cargo:rerun-if-changed=cool_rules");
+    let mut files: Vec<_> = fs::read_dir("cool_rules")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "gol3d"))
+        .collect();
+    files.sort();
+    let mut code = String::from("pub const EMBEDDED: &[(&str, &str)] = &[\n");
+    for path in files {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let path = fs::canonicalize(&path).unwrap();
+        code += &format!("    ({name:?}, include_str!({path:?})),\n");
+    }
+    code += "];\n";
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("cool_rules.rs");
+    fs::write(out, code).unwrap();
+}
@@ -1,4 +1,22 @@
-use clap::Parser;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    classify::Outcome,
+    components::Connectivity,
+    cool_rules::{self, Presets},
+    evolve::Fitness,
+    game_of_life::{GameOfLife, GOL_2D_RULE, MAX_SIZE},
+    pattern::{Orientation, Pattern, Placement},
+    rule::{Notation, Rule},
+    rule_file::{self, RuleEntry},
+    rule_parse,
+    scene::Scene,
+    Extent, Init,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -7,14 +25,420 @@ pub struct Args {
     #[arg(short, long)]
     pub file: Option<String>,
 
-    /// The size of the initial cube
-    #[arg(short = 's', long)]
-    pub init_size : Option<usize>,
+    /// Name of the rule to use from a file with several rules [default: the
+    /// first one]
+    #[arg(long, requires = "file")]
+    pub entry: Option<String>,
+
+    /// The size of the initial cube, in cells or relative to the grid like
+    /// `50%` or `0.5`
+    #[arg(short = 's', long, global = true)]
+    pub init_size: Option<Extent>,
+
+    /// The density of the intial cube, between 0 and 1
+    #[arg(
+        short = 'd',
+        long,
+        global = true,
+        value_parser = rule_parse::density
+    )]
+    pub init_density: Option<f64>,
+
+    /// Seed for the random initial cells (for `search`, the seed the rules
+    /// and soups are derived from)
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Edge length of the grid used by the headless tools (the viewer always
+    /// uses 100) [default: 100, 32 for `search`, `evolve` and `sensitivity`]
+    #[arg(long, global = true)]
+    pub grid_size: Option<usize>,
+
+    /// Number of past generations kept for stepping back in the viewer, which
+    /// reads every generation back from the GPU while enabled
+    #[arg(long, default_value_t = 0)]
+    pub history: usize,
+
+    /// Number of recent generations checked for cycles (0 disables)
+    /// [default: 256 with `--headless`, off in the viewer]
+    #[arg(long)]
+    pub cycle_window: Option<usize>,
+
+    /// Run this many generations on the CPU without opening a window
+    #[arg(long, value_name = "GENERATIONS")]
+    pub headless: Option<u64>,
+
+    /// Write per-generation statistics to this file (CSV, or JSON lines for
+    /// `.json`/`.jsonl`)
+    #[arg(long, value_name = "FILE")]
+    pub stats: Option<PathBuf>,
+
+    /// Start from a preset, see `--list-presets`
+    #[arg(long, conflicts_with_all = ["rule", "file", "scene"])]
+    pub preset: Option<String>,
+
+    /// Directory of rule files that extend the presets, besides
+    /// `cool_rules/`
+    #[arg(long, value_name = "DIR")]
+    pub preset_dir: Vec<PathBuf>,
+
+    /// Print the presets and exit
+    #[arg(long)]
+    pub list_presets: bool,
+
+    /// Start from a scene file, see `C` in the viewer
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["rule", "file"])]
+    pub scene: Option<PathBuf>,
 
-    /// The density of the intial cube
-    #[arg(short = 'd', long)]
-    pub init_density : Option<f64>,
+    /// Start from a snapshot, see `F5` in the viewer
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["rule", "file", "scene", "preset"]
+    )]
+    pub load: Option<PathBuf>,
+
+    /// Start from a pattern file (3D or 2D RLE, plaintext `.cells` or
+    /// MagicaVoxel `.vox`) instead of a random soup, with its rule if none is
+    /// given
+    #[arg(long, value_name = "FILE", global = true)]
+    pub pattern: Option<PathBuf>,
+
+    /// Number of layers along z the pattern is repeated on
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new()
+            .range(1..=MAX_SIZE as u64),
+        requires = "pattern",
+        conflicts_with = "board",
+        global = true
+    )]
+    pub layers: usize,
+
+    /// Put the pattern, or a glider, on a slab that turns its layer into 2D
+    /// Life with the rule of the B key, which is the default rule then
+    #[arg(long, global = true)]
+    pub board: bool,
+
+    /// Center of the pattern, in cells or relative to the grid like
+    /// `50%,50%,10` [default: the center of the grid]
+    #[arg(
+        long,
+        value_name = "X,Y,Z",
+        value_parser = rule_parse::position,
+        global = true
+    )]
+    pub at: Option<[Extent; 3]>,
+
+    /// How the pattern is turned, as the pattern axes along x, y and z of
+    /// the grid, e.g. `zyx` or `-yxz`
+    #[arg(
+        long,
+        default_value = "xyz",
+        allow_hyphen_values = true,
+        global = true
+    )]
+    pub orient: Orientation,
+
+    /// Generations per second in the viewer [default: one per frame, or the
+    /// speed of the scene]
+    #[arg(long)]
+    pub speed: Option<f32>,
 
     /// Pass in the rule directly
     pub rule: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// The rule given on the command line, with its initial state and the
+    /// rest of its entry if it was read from a file, falling back to a
+    /// default rule.
+    pub fn rule_entry(&self) -> anyhow::Result<RuleEntry> {
+        let mut entry = if let Some(scene) = self.scene()? {
+            scene.entry()?
+        } else if let Some(path) = &self.load {
+            let gol = GameOfLife::load(path)?;
+            RuleEntry::new(&path.display().to_string(), gol.rule, gol.init)
+        } else if let Some(r) = &self.rule {
+            let mut rule_string = r.clone();
+            rule_string.retain(|c| !c.is_whitespace());
+            let (rule, init) = rule_parse::rule_and_init(&rule_string)
+                .with_context(|| format!("could not parse rule {r}"))?;
+            RuleEntry::new(r, rule, init)
+        } else if let Some(f) = &self.file {
+            let path = Path::new(f);
+            let mut entries = rule_file::read(path)?;
+            match &self.entry {
+                Some(name) => entries
+                    .into_iter()
+                    .find(|entry| &entry.name == name)
+                    .with_context(|| {
+                        format!("{} has no rule named {name}", path.display())
+                    })?,
+                None => entries.swap_remove(0),
+            }
+        } else if let Some(name) = &self.preset {
+            self.presets()?.get(name).cloned().with_context(|| {
+                format!("no preset named {name}, see --list-presets")
+            })?
+        } else if let Some(rule) =
+            self.placement()?.and_then(|p| p.pattern.rule)
+        {
+            RuleEntry::new("pattern", rule, Init::default())
+        } else if self.board {
+            let (rule, init) = rule_parse::rule_and_init(GOL_2D_RULE)?;
+            RuleEntry::new("gol_2d", rule, init)
+        } else {
+            let (rule, init) =
+                rule_parse::rule_and_init(cool_rules::DEFAULT_RULE)?;
+            RuleEntry::new("default", rule, init)
+        };
+
+        if let Some(s) = self.init_size {
+            entry.init.size = s;
+        }
+        if let Some(d) = self.init_density {
+            entry.init.density = d;
+        }
+        if self.seed.is_some() {
+            entry.init.seed = self.seed;
+        }
+        Ok(entry)
+    }
+
+    /// The embedded presets, extended by `cool_rules/` and `--preset-dir`.
+    pub fn presets(&self) -> anyhow::Result<Presets> {
+        let mut dirs = vec![PathBuf::from(crate::FAVORITES_DIR)];
+        dirs.extend(self.preset_dir.iter().cloned());
+        Presets::load(&dirs)
+    }
+
+    /// The pattern given by `--pattern`, with where to put it.
+    pub fn placement(&self) -> anyhow::Result<Option<Placement>> {
+        let pattern = match &self.pattern {
+            Some(path) => Pattern::read(path)?.stacked(self.layers)?,
+            None if self.board => Placement::glider_board().pattern,
+            None => return Ok(None),
+        };
+        Ok(Some(Placement {
+            pattern,
+            center: self.at,
+            orientation: self.orient,
+            board: self.board,
+        }))
+    }
+
+    /// The scene given by `--scene`.
+    pub fn scene(&self) -> anyhow::Result<Option<Scene>> {
+        self.scene.as_deref().map(Scene::load).transpose()
+    }
+
+    /// The rule and initial state given on the command line, see
+    /// [`Args::rule_entry`].
+    pub fn rule_and_init(&self) -> anyhow::Result<(Rule, Init)> {
+        let entry = self.rule_entry()?;
+        Ok((entry.rule, entry.init))
+    }
+}
+
+/// Headless tools that run on the CPU instead of opening a window
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Find spaceships and report their period, displacement and speed
+    Gliders {
+        /// Number of generations to simulate
+        #[arg(short, long, default_value_t = crate::spaceship::DEFAULT_GENERATIONS)]
+        generations: u64,
+
+        /// Longest period to look for
+        #[arg(short, long, default_value_t = crate::spaceship::DEFAULT_MAX_PERIOD)]
+        max_period: u64,
+    },
+    /// Count the distinct objects after some generations
+    Census {
+        /// Number of generations to simulate first
+        #[arg(short, long, default_value_t = crate::census::DEFAULT_GENERATIONS)]
+        generations: u64,
+
+        /// Whether cells touching only at edges or corners are connected
+        #[arg(short, long, value_enum, default_value_t = Connectivity::Full)]
+        connectivity: Connectivity,
+
+        /// Write the full census as JSON to this file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Classify many random rules and save the interesting ones
+    Search {
+        /// Number of random rules to try
+        #[arg(short, long, default_value_t = 1000)]
+        count: usize,
+
+        /// Number of generations to run each rule for
+        #[arg(short, long, default_value_t = 200)]
+        generations: u64,
+
+        /// Outcomes worth saving
+        #[arg(
+            short,
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [Outcome::Periodic, Outcome::Moving]
+        )]
+        keep: Vec<Outcome>,
+
+        /// Only try rules whose Langton's lambda lies in this range
+        #[arg(long, num_args = 2, value_names = ["MIN", "MAX"])]
+        lambda: Option<Vec<f64>>,
+
+        /// Directory the interesting rules are written to
+        #[arg(short, long, default_value = "search_results")]
+        output: PathBuf,
+    },
+    /// Rank the survive and born bits by how much toggling them changes the
+    /// outcome
+    Sensitivity {
+        /// Number of generations after which the runs are compared
+        #[arg(short, long, default_value_t = crate::sensitivity::DEFAULT_GENERATIONS)]
+        generations: u64,
+    },
+    /// Count how often each neighbor count occurs and clear the bits that
+    /// never fire
+    Minimize {
+        /// Number of generations to record
+        #[arg(short, long, default_value_t = crate::histogram::DEFAULT_GENERATIONS)]
+        generations: u64,
+    },
+    /// Flip a single cell and follow how the difference spreads, as CSV
+    Damage {
+        /// Number of generations to follow the damage for
+        #[arg(short, long, default_value_t = crate::damage::DEFAULT_GENERATIONS)]
+        generations: u64,
+
+        /// Cell to flip as `x,y,z` [default: the center]
+        #[arg(short, long, value_delimiter = ',')]
+        cell: Option<Vec<usize>>,
+
+        /// File the CSV is written to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print the normalized form and ID of rules and point out duplicates
+    Normalize {
+        /// Rule files to compare [default: the rule given as usual]
+        files: Vec<PathBuf>,
+
+        /// How the normalized rules are written
+        #[arg(short, long, value_enum, default_value_t = Notation::Native)]
+        notation: Notation,
+    },
+    /// Simulate and write a region of the grid as 3D RLE pattern
+    Crop {
+        /// Generation to crop
+        #[arg(short, long, default_value_t = 0)]
+        generations: u64,
+
+        /// Lowest corner of the region as `x,y,z` [default: the bounding box
+        /// of the live cells]
+        #[arg(long, value_delimiter = ',', requires = "size")]
+        min: Option<Vec<usize>>,
+
+        /// Size of the region as `x,y,z`
+        #[arg(long, value_delimiter = ',', requires = "min")]
+        size: Option<Vec<usize>>,
+
+        /// File the pattern is written to instead of stdout, as MagicaVoxel
+        /// model if it ends in `.vox`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run a rule over a range of initial cube sizes and densities
+    Sweep {
+        /// Initial cube sizes to try
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_values_t = [
+                Extent::Cells(5),
+                Extent::Cells(10),
+                Extent::Cells(20)
+            ]
+        )]
+        sizes: Vec<Extent>,
+
+        /// Initial densities to try
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = rule_parse::density,
+            default_values_t = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9]
+        )]
+        densities: Vec<f64>,
+
+        /// Number of soups per size and density
+        #[arg(long, default_value_t = 3)]
+        seeds: u64,
+
+        /// Number of generations of every run
+        #[arg(short, long, default_value_t = crate::sweep::DEFAULT_GENERATIONS)]
+        generations: u64,
+
+        /// File every single run is written to as CSV
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run every rule of a directory or list file in parallel
+    Batch {
+        /// Directory of `.gol3d` files, a `.gol3d` file, or a file listing
+        /// rules or rule files, one per line
+        path: PathBuf,
+
+        /// Number of generations to run each rule for
+        #[arg(short, long, default_value_t = crate::batch::DEFAULT_GENERATIONS)]
+        generations: u64,
+
+        /// File the results are written to as CSV
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Evolve random rules toward a fitness target
+    Evolve {
+        /// What the rules are scored by
+        #[arg(short, long, value_enum, default_value_t = Fitness::Lifetime)]
+        fitness: Fitness,
+
+        /// Target density for the `population` fitness
+        #[arg(long, default_value_t = 0.05)]
+        target: f64,
+
+        /// Number of rules per epoch
+        #[arg(short, long, default_value_t = 32)]
+        population: usize,
+
+        /// Number of epochs to evolve for
+        #[arg(short, long, default_value_t = 20)]
+        epochs: usize,
+
+        /// Number of generations to run each rule for
+        #[arg(short, long, default_value_t = 200)]
+        generations: u64,
+
+        /// File the population is saved to after every epoch, and resumed
+        /// from if it exists
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+
+        /// Number of best rules to write
+        #[arg(short, long, default_value_t = 5)]
+        best: usize,
+
+        /// Directory the best rules are written to
+        #[arg(short, long, default_value = "evolve_results")]
+        output: PathBuf,
+    },
 }
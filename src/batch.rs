@@ -0,0 +1,183 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use rayon::prelude::*;
+
+use crate::{
+    args::Args,
+    classify::{self, Classification},
+    game_of_life::{GameOfLife, SIZE},
+    rule::Rule,
+    rule_file, rule_parse,
+    stats::GenerationStats,
+    Init,
+};
+
+/// Default number of generations every rule is run for.
+pub const DEFAULT_GENERATIONS: u64 = 100;
+
+/// A rule to run, with the name it is reported under.
+struct Entry {
+    name: String,
+    rule: anyhow::Result<(Rule, Init)>,
+}
+
+/// The outcome of one rule.
+pub struct BatchResult {
+    pub name: String,
+    pub id: String,
+    pub classification: Classification,
+    pub radius_of_gyration: Option<f64>,
+    pub time: Duration,
+}
+
+/// The rules of the `.gol3d` files in the directory `path`, of the rule file
+/// `path`, or of the list file `path`. A list holds one rule string or file
+/// path per line, relative to the list itself; empty lines and lines starting
+/// with `#` are skipped.
+fn entries(path: &Path) -> anyhow::Result<Vec<Entry>> {
+    let read = |path: PathBuf| match rule_file::read_labeled(&path) {
+        Ok(entries) => entries
+            .into_iter()
+            .map(|(name, entry)| Entry {
+                name,
+                rule: Ok((entry.rule, entry.init)),
+            })
+            .collect(),
+        Err(e) => vec![Entry {
+            name: path.display().to_string(),
+            rule: Err(e),
+        }],
+    };
+    if path.extension().is_some_and(|e| e == "gol3d") {
+        return Ok(read(path.to_path_buf()));
+    }
+    if path.is_dir() {
+        let files = rule_file::files_in(path)?;
+        return Ok(files.into_iter().flat_map(read).collect());
+    }
+
+    let list = fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .flat_map(|line| {
+            let file = dir.join(line);
+            if line.ends_with(".gol3d") || file.exists() {
+                return read(file);
+            }
+            vec![Entry {
+                name: line.to_string(),
+                rule: rule_parse::rule_and_init(line)
+                    .with_context(|| format!("invalid rule {line}")),
+            }]
+        })
+        .collect())
+}
+
+/// Run every rule in `path` for `generations` generations, in parallel, and
+/// print a summary. With `output`, the results are also written as CSV.
+///
+/// Rules without a seed of their own start from the soup given by `--seed`,
+/// or 0, so that repeated batches can be compared.
+pub fn run(
+    args: &Args,
+    path: &Path,
+    generations: u64,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let grid_size = args.grid_size.unwrap_or(SIZE);
+    let timer = Instant::now();
+    let results: Vec<anyhow::Result<BatchResult>> = entries(path)?
+        .into_par_iter()
+        .map(|Entry { name, rule }| {
+            let (rule, mut init) = rule?;
+            init.seed = init.seed.or(args.seed).or(Some(0));
+            let timer = Instant::now();
+            let mut gol = GameOfLife::new(rule, init, grid_size)?;
+            let classification = classify::classify(&mut gol, generations);
+            Ok(BatchResult {
+                name,
+                id: gol.rule.id(),
+                classification,
+                radius_of_gyration: GenerationStats::collect(&gol, None)
+                    .radius_of_gyration,
+                time: timer.elapsed(),
+            })
+        })
+        .collect();
+
+    println!(
+        "{:<40} {:>8} {:>9} {:>6} {:>10} {:>8} {:>9}",
+        "rule", "id", "outcome", "gen", "population", "radius", "time"
+    );
+    let mut outcomes = BTreeMap::new();
+    let mut failed = 0;
+    for result in &results {
+        match result {
+            Ok(r) => {
+                *outcomes.entry(r.classification.outcome).or_insert(0) += 1;
+                println!(
+                    "{:<40} {:>8} {:>9} {:>6} {:>10} {:>8.2} {:>9.2?}",
+                    r.name,
+                    r.id,
+                    r.classification.outcome,
+                    r.classification.generation,
+                    r.classification.population,
+                    r.radius_of_gyration.unwrap_or(0.),
+                    r.time
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{e:#}");
+            }
+        }
+    }
+    println!(
+        "Ran {} rules in {:.2?}, {failed} failed",
+        results.len() - failed,
+        timer.elapsed()
+    );
+    for (outcome, n) in outcomes {
+        println!("  {outcome:>9}: {n}");
+    }
+
+    if let Some(path) = output {
+        let file = File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        writeln!(
+            out,
+            "rule,id,outcome,generation,lifetime,population,\
+             radius_of_gyration,millis"
+        )?;
+        for r in results.iter().flatten() {
+            writeln!(
+                out,
+                // Rule strings contain commas.
+                "\"{}\",{},{},{},{},{},{},{}",
+                r.name.replace('"', "\"\""),
+                r.id,
+                r.classification.outcome,
+                r.classification.generation,
+                r.classification.lifetime(),
+                r.classification.population,
+                r.radius_of_gyration
+                    .map_or(String::new(), |r| r.to_string()),
+                r.time.as_millis()
+            )?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
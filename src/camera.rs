@@ -12,6 +12,9 @@ use winit::event::{
 
 use crate::game_of_life::SIZE;
 
+/// Vertical field of view in degrees.
+pub const DEFAULT_FOVY: f32 = 45.0;
+
 pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5,
     1.0,
@@ -35,7 +38,7 @@ impl Camera {
             dir: Vector3::from((-1., -1., -1.)).normalize(),
             up: cgmath::Vector3::unit_y(),
             aspect: config.width as f32 / config.height as f32,
-            fovy: 45.0,
+            fovy: DEFAULT_FOVY,
             znear: 0.1,
             zfar: 1000.0,
         };
@@ -83,12 +86,66 @@ impl Camera {
         )
     }
 
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.entity.pos = pose.pos;
+        self.entity.dir = pose.dir;
+        self.uniform.update_view_proj(&self.entity);
+    }
+
     pub fn update(&mut self, delta: f32) {
         self.controller
             .update_camera_entity(&mut self.entity, delta);
         self.uniform.update_view_proj(&self.entity);
     }
 }
+/// Where the camera is and where it looks, as given in rule files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraPose {
+    pub pos: Point3<f32>,
+    pub dir: Vector3<f32>,
+}
+
+impl std::str::FromStr for CameraPose {
+    type Err = anyhow::Error;
+
+    /// Parse `x, y, z / dx, dy, dz`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let vectors = s
+            .split('/')
+            .map(|v| {
+                let v = v
+                    .split(',')
+                    .map(|c| c.trim().parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                <[f32; 3]>::try_from(v).map_err(|_| {
+                    anyhow::anyhow!("expected three comma separated numbers")
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        match vectors[..] {
+            [pos, dir] if Vector3::from(dir) != Vector3::zero() => Ok(Self {
+                pos: pos.into(),
+                dir: Vector3::from(dir).normalize(),
+            }),
+            _ => anyhow::bail!(
+                "expected a position and a direction like \
+                 `150, 150, 150 / -1, -1, -1`"
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for CameraPose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { pos, dir } = self;
+        write!(
+            f,
+            "{}, {}, {} / {}, {}, {}",
+            pos.x, pos.y, pos.z, dir.x, dir.y, dir.z
+        )
+    }
+}
+
 pub struct CameraEntity {
     pub pos: Point3<f32>,
     pub dir: Vector3<f32>,
@@ -0,0 +1,147 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fs::File,
+    hash::{Hash, Hasher},
+    io::BufWriter,
+    path::Path,
+};
+
+use anyhow::Context;
+use ndarray::Array3;
+use serde::Serialize;
+
+use crate::{
+    components::{self, Connectivity},
+    game_of_life::GameOfLife,
+};
+
+/// Default number of generations simulated before taking the census.
+pub const DEFAULT_GENERATIONS: u64 = 100;
+
+/// The distinct objects of one generation.
+#[derive(Debug, Serialize)]
+pub struct Census {
+    pub rule: String,
+    pub generation: u64,
+    pub connectivity: usize,
+    pub objects: usize,
+    /// Number of objects per size in cells.
+    pub size_histogram: BTreeMap<usize, usize>,
+    /// All distinct shapes up to rotation and reflection, most common first.
+    pub shapes: Vec<ShapeCount>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShapeCount {
+    /// Stable identifier of the canonical shape.
+    pub code: String,
+    pub count: usize,
+    pub size: usize,
+    pub dim: [usize; 3],
+    /// The live cells of the canonical shape as `[x, y, z, state]`.
+    pub cells: Vec<[usize; 4]>,
+}
+
+impl ShapeCount {
+    fn new(shape: &Array3<u8>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        shape.dim().hash(&mut hasher);
+        shape.iter().for_each(|c| c.hash(&mut hasher));
+        let (x, y, z) = shape.dim();
+        let cells: Vec<_> = shape
+            .indexed_iter()
+            .filter(|(_, c)| **c != 0)
+            .map(|((x, y, z), c)| [x, y, z, *c as usize])
+            .collect();
+        Self {
+            code: format!("{:016x}", hasher.finish()),
+            count: 0,
+            size: cells.len(),
+            dim: [x, y, z],
+            cells,
+        }
+    }
+}
+
+impl Census {
+    pub fn take(gol: &GameOfLife, connectivity: Connectivity) -> Self {
+        let mut size_histogram = BTreeMap::new();
+        let mut shapes: HashMap<Array3<u8>, ShapeCount> = HashMap::new();
+        let objects = components::label(&gol.cells, connectivity);
+        for component in &objects {
+            *size_histogram.entry(component.cells.len()).or_default() += 1;
+            let shape = components::canonical(&component.crop(&gol.cells));
+            shapes
+                .entry(shape)
+                .or_insert_with_key(ShapeCount::new)
+                .count += 1;
+        }
+        let mut shapes: Vec<_> = shapes.into_values().collect();
+        shapes.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.size.cmp(&b.size))
+                .then_with(|| a.code.cmp(&b.code))
+        });
+
+        Self {
+            rule: gol.rule.to_string(),
+            generation: gol.generation,
+            connectivity: connectivity.num_neighbors(),
+            objects: objects.len(),
+            size_histogram,
+            shapes,
+        }
+    }
+
+    pub fn print(&self, top: usize) {
+        println!(
+            "Generation {}: {} objects, {} distinct shapes",
+            self.generation,
+            self.objects,
+            self.shapes.len()
+        );
+        println!("Size histogram:");
+        for (size, count) in &self.size_histogram {
+            println!("  {size:>6} cells: {count}");
+        }
+        println!("Most common shapes:");
+        for shape in self.shapes.iter().take(top) {
+            println!(
+                "  {} x{:<6} {:>4} cells in {}x{}x{}",
+                shape.code,
+                shape.count,
+                shape.size,
+                shape.dim[0],
+                shape.dim[1],
+                shape.dim[2]
+            );
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+/// Simulate `gol` for `generations` generations and take its census.
+pub fn run(
+    mut gol: GameOfLife,
+    generations: u64,
+    connectivity: Connectivity,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    println!("Rule: {}", gol.rule);
+    while gol.generation < generations {
+        gol.update();
+    }
+    let census = Census::take(&gol, connectivity);
+    census.print(10);
+    if let Some(path) = output {
+        census.write_json(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::fmt::Display;
+
+use crate::{
+    cycle::{Behavior, CycleDetector},
+    game_of_life::GameOfLife,
+    spaceship::SpaceshipDetector,
+};
+
+/// Fraction of live cells above which a run counts as explosive.
+pub const SATURATION_DENSITY: f64 = 0.25;
+/// Longest spaceship period looked for when classifying.
+const MAX_SPACESHIP_PERIOD: u64 = 10;
+
+/// The coarse outcome of running a rule from a random soup.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, clap::ValueEnum,
+)]
+pub enum Outcome {
+    /// All cells died.
+    Extinct,
+    /// The live cells fill a large part of the grid.
+    Explosive,
+    /// A still life.
+    Static,
+    /// An oscillator with a period above one.
+    Periodic,
+    /// Still active without repeating, but no moving objects were found.
+    Chaotic,
+    /// Still active, with at least one spaceship.
+    Moving,
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Outcome::Extinct => "extinct",
+            Outcome::Explosive => "explosive",
+            Outcome::Static => "static",
+            Outcome::Periodic => "periodic",
+            Outcome::Chaotic => "chaotic",
+            Outcome::Moving => "moving",
+        };
+        write!(f, "{name}")
+    }
+}
+
+pub struct Classification {
+    pub outcome: Outcome,
+    /// The detected cycle, if the run settled into one.
+    pub behavior: Option<Behavior>,
+    /// Generation in which the run was stopped.
+    pub generation: u64,
+    pub population: usize,
+}
+
+impl Classification {
+    /// Number of generations until the run settled into a cycle, or until
+    /// it was stopped if it never did.
+    pub fn lifetime(&self) -> u64 {
+        match self.behavior {
+            Some(
+                Behavior::Extinct { since }
+                | Behavior::Static { since }
+                | Behavior::Periodic { since, .. },
+            ) => since,
+            None => self.generation,
+        }
+    }
+}
+
+impl Display for Classification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.outcome)?;
+        if let Some(behavior) = self.behavior {
+            write!(f, " ({behavior})")?;
+        }
+        write!(
+            f,
+            " with {} cells in gen {}",
+            self.population, self.generation
+        )
+    }
+}
+
+/// Run `gol` for up to `generations` generations and classify the result.
+///
+/// Runs that settle into a cycle are stopped early. For the others, the last
+/// generations are searched for spaceships to tell moving objects apart
+/// from plain chaos.
+pub fn classify(gol: &mut GameOfLife, generations: u64) -> Classification {
+    let mut cycles = CycleDetector::new(generations as usize + 1);
+    let mut spaceships = SpaceshipDetector::new(MAX_SPACESHIP_PERIOD);
+    let watch_from = generations.saturating_sub(3 * MAX_SPACESHIP_PERIOD);
+    let mut has_spaceships = false;
+
+    let behavior = loop {
+        if let Some(behavior) = cycles.observe(gol) {
+            break Some(behavior);
+        }
+        if gol.generation >= watch_from && !has_spaceships {
+            has_spaceships = !spaceships.observe(gol).is_empty();
+        }
+        if gol.generation >= generations {
+            break None;
+        }
+        gol.update();
+    };
+
+    let population = gol.cells.iter().filter(|c| **c != 0).count();
+    let density = population as f64 / gol.cells.len() as f64;
+    let outcome = match behavior {
+        Some(Behavior::Extinct { .. }) => Outcome::Extinct,
+        _ if density > SATURATION_DENSITY => Outcome::Explosive,
+        Some(Behavior::Static { .. }) => Outcome::Static,
+        Some(Behavior::Periodic { .. }) => Outcome::Periodic,
+        None if has_spaceships => Outcome::Moving,
+        None => Outcome::Chaotic,
+    };
+    Classification {
+        outcome,
+        behavior,
+        generation: gol.generation,
+        population,
+    }
+}
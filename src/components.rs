@@ -0,0 +1,144 @@
+use ndarray::{Array3, Axis};
+
+/// Which neighbors of a cell count as connected to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Connectivity {
+    /// Only the 6 cells sharing a face.
+    #[value(name = "6")]
+    Face,
+    /// All 26 cells sharing a face, edge or corner.
+    #[value(name = "26")]
+    Full,
+}
+
+impl Connectivity {
+    pub fn num_neighbors(&self) -> usize {
+        match self {
+            Connectivity::Face => 6,
+            Connectivity::Full => 26,
+        }
+    }
+
+    fn connects(&self, offset: (isize, isize, isize)) -> bool {
+        let distance = offset.0.abs() + offset.1.abs() + offset.2.abs();
+        match self {
+            Connectivity::Face => distance == 1,
+            Connectivity::Full => distance != 0,
+        }
+    }
+}
+
+/// A connected group of live cells.
+pub struct Component {
+    pub cells: Vec<(usize, usize, usize)>,
+    pub min: [usize; 3],
+    pub max: [usize; 3],
+}
+
+impl Component {
+    /// The component cropped to its bounding box, with every cell that does
+    /// not belong to it cleared.
+    pub fn crop(&self, cells: &Array3<u8>) -> Array3<u8> {
+        let mut res = Array3::zeros((
+            self.max[0] - self.min[0] + 1,
+            self.max[1] - self.min[1] + 1,
+            self.max[2] - self.min[2] + 1,
+        ));
+        for &(x, y, z) in &self.cells {
+            res[(x - self.min[0], y - self.min[1], z - self.min[2])] =
+                cells[(x, y, z)];
+        }
+        res
+    }
+}
+
+/// Normalize `shape` under rotations and reflections, so that all 48
+/// orientations of an object map to the same array.
+pub fn canonical(shape: &Array3<u8>) -> Array3<u8> {
+    const PERMUTATIONS: [[usize; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+    let mut best: Option<Array3<u8>> = None;
+    for permutation in PERMUTATIONS {
+        for flips in 0..8 {
+            let mut view = shape.view().permuted_axes(permutation);
+            for axis in 0..3 {
+                if flips & (1 << axis) != 0 {
+                    view.invert_axis(Axis(axis));
+                }
+            }
+            let is_smaller = best.as_ref().is_none_or(|b| {
+                view.dim()
+                    .cmp(&b.dim())
+                    .then_with(|| view.iter().cmp(b.iter()))
+                    .is_lt()
+            });
+            if is_smaller {
+                best = Some(view.as_standard_layout().into_owned());
+            }
+        }
+    }
+    best.unwrap()
+}
+
+/// Label the connected components of all non-zero cells. The grid is not
+/// treated as wrapping, so an object crossing the border is split in two.
+pub fn label(cells: &Array3<u8>, connectivity: Connectivity) -> Vec<Component> {
+    let dim = cells.dim();
+    let mut visited = Array3::from_elem(dim, false);
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for (start, c) in cells.indexed_iter() {
+        if *c == 0 || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let mut component = Component {
+            cells: Vec::new(),
+            min: [start.0, start.1, start.2],
+            max: [start.0, start.1, start.2],
+        };
+        while let Some(idx) = stack.pop() {
+            component.cells.push(idx);
+            for (axis, v) in [idx.0, idx.1, idx.2].into_iter().enumerate() {
+                component.min[axis] = component.min[axis].min(v);
+                component.max[axis] = component.max[axis].max(v);
+            }
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        if !connectivity.connects((x, y, z)) {
+                            continue;
+                        }
+                        let (Some(nx), Some(ny), Some(nz)) = (
+                            idx.0.checked_add_signed(x),
+                            idx.1.checked_add_signed(y),
+                            idx.2.checked_add_signed(z),
+                        ) else {
+                            continue;
+                        };
+                        let n = (nx, ny, nz);
+                        if nx < dim.0
+                            && ny < dim.1
+                            && nz < dim.2
+                            && cells[n] != 0
+                            && !visited[n]
+                        {
+                            visited[n] = true;
+                            stack.push(n);
+                        }
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
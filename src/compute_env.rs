@@ -3,16 +3,19 @@ use wgpu::{
     include_wgsl,
     util::{BufferInitDescriptor, DeviceExt},
     BindGroup, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, Buffer, BufferUsages, ComputePipeline,
-    ComputePipelineDescriptor, Device, PipelineLayoutDescriptor, ShaderModule,
-    ShaderStages,
+    BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsages,
+    CommandEncoderDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    Device, PipelineLayoutDescriptor, Queue, ShaderModule, ShaderStages,
 };
 
+use pollster::FutureExt;
+
 use crate::{game_of_life::GameOfLife, instance::InstancesVec, rule::Rule};
 
 pub struct ComputeEnv {
     pub(crate) bind_groups_layout: BindGroupLayout,
     pub(crate) bind_groups: [BindGroup; 2],
+    pub(crate) cells_buffers: [Buffer; 2],
     pub(crate) compute_pipeline: ComputePipeline,
     pub(crate) step_toggle: usize,
     pub(crate) atomic_counter_buffer: Buffer,
@@ -95,7 +98,7 @@ impl ComputeEnv {
                     },
                 ],
             });
-        let (bind_groups, atomic_counter_buffer) =
+        let (bind_groups, cells_buffers, atomic_counter_buffer) =
             Self::generate_cells_buffers_bind_group(
                 &gol.cells,
                 device,
@@ -121,6 +124,7 @@ impl ComputeEnv {
         Self {
             bind_groups_layout,
             bind_groups,
+            cells_buffers,
             compute_pipeline,
             step_toggle: 0,
             atomic_counter_buffer,
@@ -128,13 +132,13 @@ impl ComputeEnv {
             _compute_shader: compute_shader,
         }
     }
-    fn generate_cells_buffers_bind_group(
+    pub(crate) fn generate_cells_buffers_bind_group(
         cells: &Array3<u8>,
         device: &Device,
         compute_bind_groups_layout: &BindGroupLayout,
         instances: &InstancesVec,
         rule: &Rule,
-    ) -> ([BindGroup; 2], Buffer) {
+    ) -> ([BindGroup; 2], [Buffer; 2], Buffer) {
         let cells_vec: Vec<u32> = cells
             .clone()
             .into_raw_vec()
@@ -147,7 +151,7 @@ impl ComputeEnv {
             buffers.push(device.create_buffer_init(&BufferInitDescriptor {
                 label: Some("Cells Buffer {i}"),
                 contents: bytemuck::cast_slice(&cells_vec),
-                usage: BufferUsages::STORAGE,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
             }));
         }
         let atomic_counter_buffer =
@@ -202,6 +206,52 @@ impl ComputeEnv {
             .collect::<Vec<BindGroup>>()
             .try_into()
             .unwrap();
-        (bind_groups, atomic_counter_buffer)
+        (
+            bind_groups,
+            buffers.try_into().unwrap(),
+            atomic_counter_buffer,
+        )
+    }
+
+    /// Read the current generation back from the GPU.
+    pub fn read_cells(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        dim: (usize, usize, usize),
+    ) -> Array3<u8> {
+        let size = (dim.0 * dim.1 * dim.2 * std::mem::size_of::<u32>()) as u64;
+        let staging_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Cells Staging Buffer"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder =
+            device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_buffer_to_buffer(
+            &self.cells_buffers[self.step_toggle],
+            0,
+            &staging_buffer,
+            0,
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).unwrap();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.receive().block_on().unwrap().unwrap();
+        let data = slice.get_mapped_range();
+        let cells = bytemuck::cast_slice::<_, u32>(&data)
+            .iter()
+            .map(|c| *c as u8)
+            .collect();
+        Array3::from_shape_vec(dim, cells).unwrap()
     }
 }
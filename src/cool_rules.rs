@@ -1,56 +1,152 @@
-#![allow(dead_code)]
+//! The preset rules: the files in `cool_rules` embedded at build time, and
+//! those in directories given at runtime.
 
-//! Use the files in `cool_rules` instead! Just load them with the `-f`/`--file`
-//! command line argument.
+use std::{collections::HashSet, fs, path::PathBuf};
 
-pub mod as_str {
-    pub const SHELLS: &str =
-        "3,5,7,9,11,15,17,19,21,23-24,26/3,6,8-9,11,14-17,19,24/7/M";
+use crate::rule_file::{self, RuleEntry};
 
-    pub const CRYSTAL_GROWTH: &str = "0-6/1,3/2/NN";
+// `EMBEDDED`, the name and contents of every file, generated by `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/cool_rules.rs"));
 
-    pub const CLOUDS2: &str = "12-26/13-14/2/M";
+/// The rule used when none is given.
+pub const DEFAULT_RULE: &str = "0b00110011011000101011110111001010/\
+                                0b00110101010010101101011111010000/2/M";
+/// Shipped rule files that are not presets.
+const SKIPPED: &[&str] = &["test"];
+/// Descriptions of the embedded presets, whose files only hold the rule.
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    (
+        "445",
+        "The classic 4/4/5 rule, growing from a large sparse cube",
+    ),
+    (
+        "blinkin_shape",
+        "A blinking shape in the von Neumann neighborhood",
+    ),
+    (
+        "christmas_matryoshka",
+        "Nested shells from a sparse cube, without wrapping",
+    ),
+    (
+        "city_builder",
+        "Builds blocky cities from a dense cube, restart often",
+    ),
+    ("clouds", "Cloudy blobs spreading over the whole grid"),
+    ("clouds2", "Cloudy blobs spreading over the whole grid"),
+    (
+        "crystal_growth",
+        "A crystal growing from a tiny seed, without wrapping",
+    ),
+    ("crystal_stakes", "Crystals shooting out stakes"),
+    ("dodec", "Grows a dodecahedron from a tiny seed"),
+    ("fat_gliders", "Large gliders"),
+    ("fat_gliders2", "Large gliders"),
+    ("glider_heaven", "Lots of gliders from a dense cube"),
+    (
+        "glider_heaven_small_start",
+        "Glider heaven from a tiny seed",
+    ),
+    ("gol2d", "2D Game of Life in a thin slab"),
+    ("labyrinth_box", "A box filling up with a labyrinth"),
+    ("matryoshka", "Nested shells, without wrapping"),
+    ("shells", "Nested shells"),
+    ("slow_decay", "Slowly decays from an almost full grid"),
+    ("wavy_explosion", "Explodes in waves"),
+];
 
-    pub const PERIODIC_FUNKY : &str = "0b00110011011000101011110111001010/0b00110101010010101101011111010000/2/M";
+/// All presets by name, sorted.
+pub struct Presets {
+    entries: Vec<RuleEntry>,
 }
 
-pub mod as_rule {
-    use crate::rule::{Neighborhood, Rule};
-
-    pub const DODEC: Rule = Rule {
-        survive_mask: 0b00010000101000110110100001000010,
-        born_mask: 0b10100110001110111001000011111000,
-        max_state: 1,
-        neighborhood: Neighborhood::Moore,
-    };
-    pub const WAVY_EXPLOSION: Rule = Rule {
-        survive_mask: 0b01110101000100101010001010011110,
-        born_mask: 0b01001011101111001000101011001000,
-        max_state: 4,
-        neighborhood: Neighborhood::Moore,
-    };
-    pub const LABYRINTH_BOX: Rule = Rule {
-        survive_mask: 0b01001111100100001010101100100000,
-        born_mask: 0b00001111000101000101100000011110,
-        max_state: 4,
-        neighborhood: Neighborhood::Moore,
-    };
-    pub const CITY_BUILER: Rule = Rule {
-        survive_mask: 0b10111011110010111010111000011110,
-        born_mask: 0b01010010000011010101001001110000,
-        max_state: 4,
-        neighborhood: Neighborhood::Moore,
-    }; // ^ restart often
-    pub const GLIDER_HEAVEN: Rule = Rule {
-        survive_mask: 0b00110011011000101011110111001010,
-        born_mask: 0b00110101010010101101011111010000,
-        max_state: 2,
-        neighborhood: Neighborhood::Moore,
-    };
-    pub const PERIODIC_FUNKY: Rule = Rule {
-        survive_mask: 0b01111000000011000100000001000010,
-        born_mask: 0b11101011011001101100000010010000,
-        max_state: 4,
-        neighborhood: Neighborhood::Moore,
-    };
+impl Presets {
+    /// The presets built into the binary. Files with the same rule and
+    /// initial cube as one before them are left out.
+    pub fn embedded() -> Self {
+        let mut presets = Self {
+            entries: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        for (name, text) in EMBEDDED {
+            if SKIPPED.contains(name) {
+                continue;
+            }
+            let mut entries = rule_file::parse(text, name)
+                .unwrap_or_else(|e| panic!("invalid preset {name}: {e:#}"));
+            entries.retain(|entry| {
+                seen.insert(format!("{}/{}", entry.rule, entry.init))
+            });
+            for entry in &mut entries {
+                entry.description = entry.description.take().or_else(|| {
+                    DESCRIPTIONS
+                        .iter()
+                        .find(|(n, _)| *n == entry.name)
+                        .map(|(_, d)| d.to_string())
+                });
+            }
+            presets.extend(entries);
+        }
+        presets
+    }
+
+    /// The embedded presets and the rule files in `dirs`, which replace
+    /// embedded presets of the same name. Missing directories are skipped,
+    /// and so are unchanged copies of the shipped files like `cool_rules/`
+    /// when running from the repository.
+    pub fn load(dirs: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut presets = Self::embedded();
+        for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+            for path in rule_file::files_in(dir)? {
+                let shipped = EMBEDDED.iter().any(|(name, text)| {
+                    path.file_stem().is_some_and(|stem| stem == *name)
+                        && fs::read_to_string(&path).is_ok_and(|t| t == *text)
+                });
+                if shipped {
+                    continue;
+                }
+                presets.extend(rule_file::read(&path)?);
+            }
+        }
+        Ok(presets)
+    }
+
+    fn extend(&mut self, entries: Vec<RuleEntry>) {
+        for entry in entries {
+            match self.entries.binary_search_by(|e| e.name.cmp(&entry.name)) {
+                Ok(i) => self.entries[i] = entry,
+                Err(i) => self.entries.insert(i, entry),
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RuleEntry> {
+        self.position(name).map(|i| &self.entries[i])
+    }
+
+    /// Index of the preset `name` in [`Presets::entries`].
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    pub fn entries(&self) -> &[RuleEntry] {
+        &self.entries
+    }
+}
+
+/// Print the name, description and rule of every preset.
+pub fn list(presets: &Presets) {
+    let width = presets
+        .entries()
+        .iter()
+        .map(|entry| entry.name.len())
+        .max()
+        .unwrap_or_default();
+    for entry in presets.entries() {
+        println!(
+            "{:<width$}  {}",
+            entry.name,
+            entry.description.as_deref().unwrap_or("-")
+        );
+        println!("{:<width$}  {}/{}", "", entry.rule, entry.init);
+    }
 }
@@ -0,0 +1,117 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use crate::game_of_life::GameOfLife;
+
+/// Default number of recent generations compared against.
+pub const DEFAULT_WINDOW: usize = 256;
+
+/// What a simulation has settled into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    /// No cell is alive since `since`.
+    Extinct { since: u64 },
+    /// The grid stopped changing at generation `since`.
+    Static { since: u64 },
+    /// The grid repeats every `period` generations, starting at `since`.
+    Periodic { period: u64, since: u64 },
+}
+
+impl Display for Behavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Behavior::Extinct { since } => {
+                write!(f, "extinct since gen {since}")
+            }
+            Behavior::Static { since } => write!(f, "static since gen {since}"),
+            Behavior::Periodic { period, since } => {
+                write!(f, "period {period} since gen {since}")
+            }
+        }
+    }
+}
+
+/// Detects still lifes, oscillators and extinction by hashing every
+/// generation and looking the hash up among the last `window` generations.
+pub struct CycleDetector {
+    window: usize,
+    recent: VecDeque<(u64, u64)>,
+    lookup: HashMap<u64, u64>,
+    behavior: Option<Behavior>,
+}
+
+impl CycleDetector {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            recent: VecDeque::with_capacity(window),
+            lookup: HashMap::with_capacity(window),
+            behavior: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.window > 0
+    }
+
+    pub fn behavior(&self) -> Option<Behavior> {
+        self.behavior
+    }
+
+    /// Forget everything seen so far, e.g. after a reset or a jump through
+    /// the history.
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.lookup.clear();
+        self.behavior = None;
+    }
+
+    /// Look at the current generation of `gol`. Returns the behavior once it
+    /// has been detected; the simulation is deterministic, so it sticks until
+    /// [`CycleDetector::clear`] is called.
+    pub fn observe(&mut self, gol: &GameOfLife) -> Option<Behavior> {
+        if !self.is_enabled() || self.behavior.is_some() {
+            return self.behavior;
+        }
+        let generation = gol.generation;
+        if gol.cells.iter().all(|c| *c == 0) {
+            self.behavior = Some(Behavior::Extinct { since: generation });
+            return self.behavior;
+        }
+
+        let hash = grid_hash(gol);
+        if let Some(&since) = self.lookup.get(&hash) {
+            let period = generation - since;
+            self.behavior = Some(if period == 1 {
+                Behavior::Static { since }
+            } else {
+                Behavior::Periodic { period, since }
+            });
+            return self.behavior;
+        }
+
+        if self.recent.len() == self.window {
+            if let Some((old_hash, old_generation)) = self.recent.pop_front() {
+                if self.lookup.get(&old_hash) == Some(&old_generation) {
+                    self.lookup.remove(&old_hash);
+                }
+            }
+        }
+        self.recent.push_back((hash, generation));
+        self.lookup.insert(hash, generation);
+        None
+    }
+}
+
+fn grid_hash(gol: &GameOfLife) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    gol.cells.dim().hash(&mut hasher);
+    match gol.cells.as_slice() {
+        Some(cells) => cells.hash(&mut hasher),
+        None => gol.cells.iter().for_each(|c| c.hash(&mut hasher)),
+    }
+    hasher.finish()
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use cgmath::vec3;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    Buffer, BufferUsages, Device,
+};
+
+use crate::{
+    game_of_life::GameOfLife,
+    instance::{Instance, RawInstance},
+};
+
+/// Default number of generations the damage is followed for.
+pub const DEFAULT_GENERATIONS: u64 = 200;
+/// Instance state that `draw.wgsl` renders as part of the damage overlay.
+pub const OVERLAY_STATE: u32 = u32::MAX;
+
+/// How far the difference between the two copies has spread.
+#[derive(Debug, Clone, Copy)]
+pub struct DamageStats {
+    pub generation: u64,
+    /// Number of cells whose state differs between the copies.
+    pub hamming: usize,
+    pub bounding_box: Option<[[usize; 3]; 2]>,
+    /// Longest side of the bounding box.
+    pub extent: usize,
+}
+
+impl DamageStats {
+    const CSV_HEADER: &'static str =
+        "generation,hamming,min_x,min_y,min_z,max_x,max_y,max_z,extent";
+
+    fn csv_row(&self) -> String {
+        let mut row = format!("{},{}", self.generation, self.hamming);
+        match self.bounding_box {
+            Some([min, max]) => {
+                for v in min.iter().chain(max.iter()) {
+                    row += &format!(",{v}");
+                }
+            }
+            None => row += ",,,,,,",
+        }
+        row + &format!(",{}", self.extent)
+    }
+}
+
+/// A copy of a simulation with a single cell flipped, for following how a
+/// small perturbation spreads.
+pub struct Damage {
+    pub twin: GameOfLife,
+}
+
+impl Damage {
+    /// Copy `gol` and flip `cell` in the copy, or the center cell if `None`.
+    pub fn new(gol: &GameOfLife, cell: Option<[usize; 3]>) -> Self {
+        let mut twin = gol.clone();
+        let (x, y, z) = twin.cells.dim();
+        let [x, y, z] = cell.unwrap_or([x / 2, y / 2, z / 2]);
+        let c = &mut twin.cells[(x, y, z)];
+        *c = if *c == 0 { twin.rule.max_state } else { 0 };
+        Self { twin }
+    }
+
+    /// Positions of the cells that differ between `gol` and the copy.
+    pub fn differences<'a>(
+        &'a self,
+        gol: &'a GameOfLife,
+    ) -> impl Iterator<Item = [usize; 3]> + 'a {
+        gol.cells
+            .indexed_iter()
+            .zip(self.twin.cells.iter())
+            .filter(|((_, a), b)| a != b)
+            .map(|(((x, y, z), _), _)| [x, y, z])
+    }
+
+    pub fn measure(&self, gol: &GameOfLife) -> DamageStats {
+        let mut hamming = 0;
+        let mut min = [usize::MAX; 3];
+        let mut max = [0; 3];
+        for cell in self.differences(gol) {
+            hamming += 1;
+            for axis in 0..3 {
+                min[axis] = min[axis].min(cell[axis]);
+                max[axis] = max[axis].max(cell[axis]);
+            }
+        }
+        let bounding_box = (hamming > 0).then_some([min, max]);
+        DamageStats {
+            generation: gol.generation,
+            hamming,
+            bounding_box,
+            extent: bounding_box.map_or(0, |[min, max]| {
+                (0..3).map(|i| max[i] - min[i] + 1).max().unwrap()
+            }),
+        }
+    }
+}
+
+/// A [`Damage`] whose difference set is drawn on top of the cells.
+pub struct DamageOverlay {
+    pub damage: Damage,
+    pub stats: DamageStats,
+    pub buffer: Buffer,
+    pub num_instances: u32,
+}
+
+impl DamageOverlay {
+    pub fn new(gol: &GameOfLife, device: &Device) -> Self {
+        let damage = Damage::new(gol, None);
+        let (buffer, num_instances) = Self::instances(&damage, gol, device);
+        Self {
+            stats: damage.measure(gol),
+            damage,
+            buffer,
+            num_instances,
+        }
+    }
+
+    /// Advance the copy along with `gol`, which has just been stepped.
+    pub fn step(&mut self, gol: &GameOfLife, device: &Device) {
+        self.damage.twin.update();
+        self.stats = self.damage.measure(gol);
+        (self.buffer, self.num_instances) =
+            Self::instances(&self.damage, gol, device);
+    }
+
+    fn instances(
+        damage: &Damage,
+        gol: &GameOfLife,
+        device: &Device,
+    ) -> (Buffer, u32) {
+        let mut raw: Vec<_> = damage
+            .differences(gol)
+            .map(|[x, y, z]| {
+                RawInstance::new(&Instance {
+                    position: vec3(x as _, y as _, z as _),
+                    state: OVERLAY_STATE,
+                })
+            })
+            .collect();
+        let num_instances = raw.len() as u32;
+        // Buffers must not be empty.
+        if raw.is_empty() {
+            raw.push(RawInstance::new(&Instance {
+                position: vec3(0., 0., 0.),
+                state: OVERLAY_STATE,
+            }));
+        }
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Damage Instance Buffer"),
+            contents: bytemuck::cast_slice(&raw),
+            usage: BufferUsages::VERTEX,
+        });
+        (buffer, num_instances)
+    }
+}
+
+/// Follow the damage caused by flipping `cell` for `generations` generations
+/// and write one CSV row per generation to `output`, or to stdout.
+pub fn run(
+    mut gol: GameOfLife,
+    generations: u64,
+    cell: Option<&[usize]>,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).with_context(|| {
+                format!("could not create {}", path.display())
+            })?))
+        }
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+    let dim = gol.cells.dim();
+    let cell = match cell {
+        Some(&[x, y, z]) if x < dim.0 && y < dim.1 && z < dim.2 => {
+            Some([x, y, z])
+        }
+        Some(_) => anyhow::bail!(
+            "the cell must be given as x,y,z inside the {}x{}x{} grid",
+            dim.0,
+            dim.1,
+            dim.2
+        ),
+        None => None,
+    };
+    let mut damage = Damage::new(&gol, cell);
+    writeln!(out, "{}", DamageStats::CSV_HEADER)?;
+    let mut stats = damage.measure(&gol);
+    while gol.generation < generations {
+        writeln!(out, "{}", stats.csv_row())?;
+        gol.update();
+        damage.twin.update();
+        stats = damage.measure(&gol);
+    }
+    writeln!(out, "{}", stats.csv_row())?;
+    out.flush()?;
+
+    if output.is_some() {
+        println!("Rule: {}", gol.rule);
+        println!(
+            "Generation {}: {} cells differ, extent {}",
+            stats.generation, stats.hamming, stats.extent
+        );
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
 //TODO
 const MAX_STATE = 5.;
+// Keep in sync with `damage::OVERLAY_STATE`.
+const OVERLAY_STATE = 4294967295u;
 
 
 struct VertexOutput {
@@ -17,6 +19,12 @@ struct VertexInput {
 struct CameraUniform {
     view_proj: mat4x4<f32>
 }
+// Keep in sync with `palette::PaletteUniform`.
+struct Palette {
+    colors: array<vec4<f32>, 8>,
+    len: u32,
+    max_state: u32,
+}
 struct InstanceInput {
     @location(2) pos: vec3<f32>,
     @location(3) state: u32
@@ -24,6 +32,8 @@ struct InstanceInput {
 
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
+@group(1) @binding(0)
+var<uniform> palette: Palette;
 
 
 
@@ -32,9 +42,22 @@ fn vs_main(
     model: VertexInput,
     instance: InstanceInput
 ) -> VertexOutput {
-    let pos = camera.view_proj * ((vec4<f32>(instance.pos, 1.0) + vec4<f32>(model.position, 1.0)));
+    var position = model.position;
     let state_f32 = f32(instance.state)/MAX_STATE;
-    let color = vec4<f32>(state_f32,state_f32, state_f32, 1.0);
+    var color = vec4<f32>(state_f32,state_f32, state_f32, 1.0);
+    if palette.len > 0u {
+        // Newborn cells get the first color, dying ones the last.
+        let age = f32(palette.max_state - min(instance.state, palette.max_state));
+        let t = age / f32(max(palette.max_state, 2u) - 1u) * f32(palette.len - 1u);
+        let i = u32(t);
+        color = mix(palette.colors[i], palette.colors[min(i + 1u, palette.len - 1u)], fract(t));
+    }
+    if instance.state == OVERLAY_STATE {
+        // Slightly larger than a cell, so that it covers it.
+        position *= 1.1;
+        color = vec4<f32>(1.0, 0.2, 0.1, 1.0);
+    }
+    let pos = camera.view_proj * ((vec4<f32>(instance.pos, 1.0) + vec4<f32>(position, 1.0)));
     let shade = (1. + dot(model.normal, normalize(vec3<f32>(1., 2., -1.)))) * 0.5;
     return VertexOutput(pos, color, shade, instance.state);
 }
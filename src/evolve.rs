@@ -0,0 +1,344 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use ndarray::Array3;
+use ndarray_rand::rand::{
+    self, rngs::StdRng, seq::SliceRandom, Rng, SeedableRng,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    args::Args,
+    classify::SATURATION_DENSITY,
+    cycle::{Behavior, CycleDetector},
+    game_of_life::GameOfLife,
+    rule::Rule,
+    search::{self, DEFAULT_GRID_SIZE},
+    spaceship::SpaceshipDetector,
+    Init,
+};
+
+/// Longest spaceship period looked for by [`Fitness::Gliders`].
+const MAX_SPACESHIP_PERIOD: u64 = 10;
+
+/// What the evolution optimizes for.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Fitness {
+    /// Stay active for as long as possible without filling the grid.
+    Lifetime,
+    /// End up mirror symmetric along the three axes.
+    Symmetry,
+    /// End up with a density close to `--target`.
+    Population,
+    /// Produce as many kinds of spaceships as possible.
+    Gliders,
+}
+
+/// Settings shared by every candidate of a run.
+pub struct Evolution {
+    pub fitness: Fitness,
+    /// Target density for [`Fitness::Population`].
+    pub target: f64,
+    /// Number of generations each candidate is simulated for.
+    pub generations: u64,
+    pub grid_size: usize,
+    pub init: Init,
+}
+
+/// The state of an evolution, written to disk after every epoch so that an
+/// interrupted run can be resumed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub seed: u64,
+    pub epoch: usize,
+    pub rules: Vec<String>,
+    /// The objective of the run, which a resumed run has to keep.
+    pub fitness: Fitness,
+    pub target: f64,
+    pub generations: u64,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("could not open {}", path.display()))?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Fail if the checkpoint was written by a run scored differently.
+    fn check(&self, evolution: &Evolution) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.fitness == evolution.fitness
+                && self.target == evolution.target
+                && self.generations == evolution.generations,
+            "the checkpoint was written with --fitness {} --target {} \
+             --generations {}, resume with the same ones",
+            self.fitness.to_possible_value().unwrap().get_name(),
+            self.target,
+            self.generations
+        );
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    fn population(&self) -> anyhow::Result<Vec<Rule>> {
+        anyhow::ensure!(!self.rules.is_empty(), "the checkpoint has no rules");
+        self.rules
+            .iter()
+            .map(|r| {
+                r.parse()
+                    .with_context(|| format!("invalid rule in checkpoint: {r}"))
+            })
+            .collect()
+    }
+}
+
+impl Evolution {
+    /// Simulate `rule` from the soup seeded with `seed` and score the result.
+    /// Higher is better; extinct and explosive runs score zero.
+    pub fn score(&self, rule: Rule, seed: u64) -> anyhow::Result<f64> {
+        let init = Init {
+            seed: Some(seed),
+            ..self.init.clone()
+        };
+        let mut gol = GameOfLife::new(rule, init, self.grid_size)?;
+        let mut cycles = CycleDetector::new(self.generations as usize + 1);
+        let mut spaceships = SpaceshipDetector::new(MAX_SPACESHIP_PERIOD);
+        let watch_from =
+            self.generations.saturating_sub(3 * MAX_SPACESHIP_PERIOD);
+        let mut num_spaceships = 0;
+        let settled = loop {
+            if let Some(behavior) = cycles.observe(&gol) {
+                break Some(behavior);
+            }
+            if self.fitness == Fitness::Gliders && gol.generation >= watch_from
+            {
+                num_spaceships += spaceships.observe(&gol).len();
+            }
+            if gol.generation >= self.generations {
+                break None;
+            }
+            gol.update();
+        };
+
+        let population = gol.cells.iter().filter(|c| **c != 0).count();
+        let density = population as f64 / gol.cells.len() as f64;
+        if population == 0 || density > SATURATION_DENSITY {
+            return Ok(0.);
+        }
+        Ok(match self.fitness {
+            Fitness::Lifetime => {
+                let lifetime = match settled {
+                    Some(
+                        Behavior::Static { since }
+                        | Behavior::Periodic { since, .. },
+                    ) => since,
+                    _ => self.generations,
+                };
+                lifetime as f64 / self.generations as f64
+            }
+            Fitness::Symmetry => symmetry(&gol.cells),
+            Fitness::Population => {
+                1. - ((density - self.target).abs() / self.target).min(1.)
+            }
+            Fitness::Gliders => num_spaceships as f64,
+        })
+    }
+}
+
+/// Fraction of live cells whose mirror image inside the bounding box is live
+/// as well, averaged over the three axes.
+fn symmetry(cells: &Array3<u8>) -> f64 {
+    let live: Vec<_> = cells
+        .indexed_iter()
+        .filter(|(_, c)| **c != 0)
+        .map(|((x, y, z), _)| [x, y, z])
+        .collect();
+    let mut min = [usize::MAX; 3];
+    let mut max = [0; 3];
+    for cell in &live {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(cell[axis]);
+            max[axis] = max[axis].max(cell[axis]);
+        }
+    }
+    let mirrored: usize = (0..3)
+        .map(|axis| {
+            live.iter()
+                .filter(|cell| {
+                    let mut mirror = **cell;
+                    mirror[axis] = min[axis] + max[axis] - cell[axis];
+                    cells[mirror] != 0
+                })
+                .count()
+        })
+        .sum();
+    mirrored as f64 / (3 * live.len()) as f64
+}
+
+/// Flip one random bit, and occasionally change the number of states or the
+/// neighborhood.
+fn mutate<R: Rng>(rule: &mut Rule, rng: &mut R) {
+    rule.flip_random_bit(rng);
+    if rng.gen_bool(0.2) {
+        rule.change_states(if rng.gen() { 1 } else { -1 });
+    }
+    if rng.gen_bool(0.1) {
+        rule.neighborhood = rng.sample(rand::distributions::Standard);
+    }
+}
+
+/// Take every survive and born bit, the number of states and the
+/// neighborhood from either parent at random.
+fn crossover<R: Rng>(a: &Rule, b: &Rule, rng: &mut R) -> Rule {
+    let survive: u32 = rng.gen();
+    let born: u32 = rng.gen();
+    Rule {
+        survive_mask: (a.survive_mask & survive) | (b.survive_mask & !survive),
+        born_mask: (a.born_mask & born) | (b.born_mask & !born),
+        max_state: if rng.gen() { a.max_state } else { b.max_state },
+        neighborhood: if rng.gen() {
+            a.neighborhood
+        } else {
+            b.neighborhood
+        },
+    }
+}
+
+/// Evolve a population of random rules toward `fitness` for `epochs`
+/// epochs and write the `best` rules to `output` as `.gol3d` files.
+///
+/// The rules of an epoch all start from the same soup, so that their scores
+/// are comparable. Everything random is derived from `--seed` and the epoch,
+/// which makes a run, resumed from its checkpoint or not, repeatable.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    args: &Args,
+    fitness: Fitness,
+    target: f64,
+    population: usize,
+    epochs: usize,
+    generations: u64,
+    checkpoint: Option<&Path>,
+    best: usize,
+    output: &Path,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(population > 0, "the population needs at least one rule");
+    anyhow::ensure!(
+        fitness != Fitness::Population || target > 0.,
+        "the target density needs to be above 0"
+    );
+    let evolution = Evolution {
+        fitness,
+        target,
+        generations,
+        grid_size: args.grid_size.unwrap_or(DEFAULT_GRID_SIZE),
+        init: search::soup(args),
+    };
+    let mut state = match checkpoint.filter(|path| path.exists()) {
+        Some(path) => {
+            let state = Checkpoint::load(path)?;
+            state.check(&evolution)?;
+            println!(
+                "Resuming from {} at epoch {} with seed {}",
+                path.display(),
+                state.epoch,
+                state.seed
+            );
+            state
+        }
+        None => {
+            let seed = args.seed.unwrap_or_else(rand::random);
+            println!("Evolving {population} rules with seed {seed}");
+            let mut rng = StdRng::seed_from_u64(seed);
+            Checkpoint {
+                seed,
+                epoch: 0,
+                rules: (0..population)
+                    .map(|_| Rule::random_using(&mut rng).to_string())
+                    .collect(),
+                fitness,
+                target,
+                generations,
+            }
+        }
+    };
+    let mut rules = state.population()?;
+    let elite = (rules.len() / 4).max(1);
+
+    loop {
+        let mut rng =
+            StdRng::seed_from_u64(state.seed.wrapping_add(state.epoch as u64));
+        let soup_seed = rng.gen();
+        let mut scored: Vec<(f64, Rule)> = rules
+            .into_par_iter()
+            .map(|rule| Ok((evolution.score(rule.clone(), soup_seed)?, rule)))
+            .collect::<anyhow::Result<_>>()?;
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mean =
+            scored.iter().map(|(s, _)| s).sum::<f64>() / scored.len() as f64;
+        println!(
+            "Epoch {}: best {:.3} ({}), mean {mean:.3}",
+            state.epoch, scored[0].0, scored[0].1
+        );
+
+        if state.epoch >= epochs {
+            fs::create_dir_all(output).with_context(|| {
+                format!("could not create {}", output.display())
+            })?;
+            // The elite is copied into every epoch, so the same rule tends
+            // to appear several times.
+            let mut seen = HashSet::new();
+            let unique =
+                scored.iter().filter(|(_, rule)| seen.insert(rule.id()));
+            // The soup the rules were scored on.
+            let init = Init {
+                seed: Some(soup_seed),
+                ..evolution.init.clone()
+            };
+            for (rank, (score, rule)) in unique.take(best).enumerate() {
+                let path = output
+                    .join(format!("{fitness:?}_{rank}_{}.gol3d", rule.id()));
+                fs::write(&path, format!("{}/{init}", rule.normalized()))
+                    .with_context(|| {
+                        format!("could not write {}", path.display())
+                    })?;
+                println!("{score:.3} {rule} -> {}", path.display());
+            }
+            return Ok(());
+        }
+
+        // The best quarter survives unchanged, the rest of the population
+        // are children of two parents from the better half.
+        let parents = &scored[..(scored.len() / 2).max(1)];
+        rules = scored[..elite].iter().map(|(_, r)| r.clone()).collect();
+        while rules.len() < scored.len() {
+            let (_, a) = parents.choose(&mut rng).unwrap();
+            let (_, b) = parents.choose(&mut rng).unwrap();
+            let mut child = crossover(a, b, &mut rng);
+            mutate(&mut child, &mut rng);
+            rules.push(child);
+        }
+        state.epoch += 1;
+        state.rules = rules.iter().map(Rule::to_string).collect();
+        if let Some(path) = checkpoint {
+            state.save(path)?;
+        }
+    }
+}
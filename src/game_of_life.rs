@@ -1,71 +1,174 @@
-use crate::{rule::Rule, Init};
+use crate::{args::Args, rule::Rule, snapshot, Init};
+use anyhow::Context;
 use ndarray::Array3;
-use ndarray_rand::RandomExt;
+use ndarray_rand::{
+    rand::{rngs::StdRng, thread_rng, Rng, SeedableRng},
+    RandomExt,
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
 
 pub const SIZE: usize = 100;
+/// The rule that runs Conway's Life on [`GameOfLife::gol_2d_board`].
+pub const GOL_2D_RULE: &str = "1-8,11-12,17-31/12/2/M";
+/// Largest edge length of a grid or pattern read from a file.
+pub const MAX_SIZE: usize = 512;
+#[derive(Clone)]
 pub struct GameOfLife {
     pub cells: Array3<u8>,
     pub rule: Rule,
     pub init: Init,
+    pub generation: u64,
+}
+
+impl TryFrom<&Args> for GameOfLife {
+    type Error = anyhow::Error;
+
+    fn try_from(args: &Args) -> anyhow::Result<Self> {
+        let (rule, init) = args.rule_and_init()?;
+        if let Some(path) = &args.load {
+            let gol = GameOfLife::load(path)?;
+            if let Some(size) = args.grid_size {
+                if gol.cells.dim() != (size, size, size) {
+                    anyhow::bail!(
+                        "the snapshot has a grid of {:?}, not {size}",
+                        gol.cells.dim()
+                    );
+                }
+            }
+            return Ok(GameOfLife { rule, init, ..gol });
+        }
+        let size = match args.scene()? {
+            Some(scene) => args.grid_size.unwrap_or(scene.grid.size),
+            None => args.grid_size.unwrap_or(SIZE),
+        };
+        if let Some(placement) = args.placement()? {
+            return Ok(GameOfLife {
+                cells: placement.cells(size, rule.max_state),
+                rule,
+                init,
+                generation: 0,
+            });
+        }
+        GameOfLife::new(rule, init, size)
+    }
 }
 
 impl GameOfLife {
-    pub fn cells_random(
+    pub fn new(rule: Rule, init: Init, size: usize) -> anyhow::Result<Self> {
+        Ok(GameOfLife {
+            cells: GameOfLife::cells_random_init(size, rule.max_state, &init)?,
+            rule,
+            init,
+            generation: 0,
+        })
+    }
+
+    /// A random cube of edge length `partial_size` centered on `center`, or
+    /// the center of the grid, and moved inside the grid if needed.
+    pub fn cells_random<R: Rng>(
         size: usize,
         partial_size: usize,
+        center: Option<[usize; 3]>,
         prob: f64,
         max_state: u8,
-    ) -> Array3<u8> {
+        rng: &mut R,
+    ) -> anyhow::Result<Array3<u8>> {
+        let density = ndarray_rand::rand_distr::Bernoulli::new(prob)
+            .with_context(|| format!("invalid density {prob}"))?;
+        let partial_size = partial_size.min(size);
+        let [x, y, z] = match center {
+            Some(center) => center.map(|c| {
+                c.saturating_sub(partial_size / 2).min(size - partial_size)
+            }),
+            None => [(size - partial_size) / 2; 3],
+        };
         let mut cells = Array3::<u8>::zeros((size, size, size));
         cells
             .slice_mut(ndarray::s![
-                ((size - partial_size) / 2)..((size + partial_size) / 2),
-                ((size - partial_size) / 2)..((size + partial_size) / 2),
-                ((size - partial_size) / 2)..((size + partial_size) / 2),
+                x..x + partial_size,
+                y..y + partial_size,
+                z..z + partial_size,
             ])
             .assign(
-                &Array3::<bool>::random(
+                &Array3::<bool>::random_using(
                     (partial_size, partial_size, partial_size),
-                    ndarray_rand::rand_distr::Bernoulli::new(prob).unwrap(),
+                    density,
+                    rng,
                 )
                 .map(|v| u8::from(*v) * max_state),
             );
-        cells
+        Ok(cells)
     }
 
+    /// An empty grid but for two full layers below the layer `height` along
+    /// y. With [`GOL_2D_RULE`] a 2D pattern on that layer follows Conway's
+    /// Life: the slab adds 9 to the neighbor count of every cell on it, and
+    /// keeps the layers above and below from coming alive.
     pub fn gol_2d_board(
         size: usize,
-        partial_size: usize,
-        prob: f64,
+        height: usize,
         max_state: u8,
     ) -> Array3<u8> {
         let mut cells = Array3::<u8>::zeros((size, size, size));
-        cells
-            .slice_mut(ndarray::s![
-                ((size - partial_size) / 2)..((size + partial_size) / 2),
-                9..11,
-                ((size - partial_size) / 2)..((size + partial_size) / 2),
-            ])
-            .assign(
-                &Array3::<bool>::random(
-                    (partial_size, 1, partial_size),
-                    ndarray_rand::rand_distr::Bernoulli::new(prob).unwrap(),
-                )
-                .map(|v| u8::from(*v) * max_state),
-            );
-        cells[((size) / 2, 11, (size) / 2)] = max_state;
-        cells[((size) / 2 + 1, 11, (size) / 2)] = max_state;
-        cells[((size) / 2 + 2, 11, (size) / 2)] = max_state;
-        cells[((size) / 2 + 2, 11, (size) / 2 + 1)] = max_state;
-        cells[((size) / 2 + 1, 11, (size) / 2 + 2)] = max_state;
+        for below in 1..=2 {
+            let y = (height + size - below) % size;
+            cells.slice_mut(ndarray::s![.., y, ..]).fill(max_state);
+        }
         cells
     }
-    pub fn cells_random_init(max_state: u8, init: &Init) -> Array3<u8> {
-        Self::cells_random(SIZE, init.size, init.density, max_state)
+    /// A random soup as described by `init`, reproducible if it has a seed.
+    pub fn cells_random_init(
+        size: usize,
+        max_state: u8,
+        init: &Init,
+    ) -> anyhow::Result<Array3<u8>> {
+        let partial_size = init.size.resolve(size);
+        let center = init.position.map(|p| p.map(|c| c.resolve(size)));
+        match init.seed {
+            Some(seed) => Self::cells_random(
+                size,
+                partial_size,
+                center,
+                init.density,
+                max_state,
+                &mut StdRng::seed_from_u64(seed),
+            ),
+            None => Self::cells_random(
+                size,
+                partial_size,
+                center,
+                init.density,
+                max_state,
+                &mut thread_rng(),
+            ),
+        }
     }
 
     pub fn cells_random_preset(max_state: u8) -> Array3<u8> {
-        Self::cells_random(SIZE, 2, 1., max_state)
+        Self::cells_random(SIZE, 2, None, 1., max_state, &mut thread_rng())
+            .unwrap()
+    }
+
+    /// Write a binary snapshot of the grid to `path`, see `snapshot.rs`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        snapshot::write(self, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a snapshot written by [`GameOfLife::save`].
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        snapshot::read(BufReader::new(file))
+            .with_context(|| format!("invalid snapshot {}", path.display()))
     }
 
     pub fn update(&mut self) {
@@ -80,5 +183,6 @@ impl GameOfLife {
                 *c = c.saturating_sub(1);
             }
         }
+        self.generation += 1;
     }
 }
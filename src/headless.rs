@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use anyhow::Context;
+
+use crate::{
+    args::Args, cycle::CycleDetector, game_of_life::GameOfLife,
+    stats::StatsWriter,
+};
+
+/// Run the CPU engine for up to `generations` generations, stopping early
+/// once it has settled into a cycle or died out.
+pub fn run(args: &Args, generations: u64) -> anyhow::Result<()> {
+    let mut gol = GameOfLife::try_from(args)?;
+    println!("Rule: {} ({})", gol.rule, gol.rule.analysis());
+    let timer = Instant::now();
+    let mut cycles = CycleDetector::new(
+        args.cycle_window.unwrap_or(crate::cycle::DEFAULT_WINDOW),
+    );
+    let mut stats = match &args.stats {
+        Some(path) => {
+            Some(StatsWriter::create(path).with_context(|| {
+                format!("could not create {}", path.display())
+            })?)
+        }
+        None => None,
+    };
+
+    loop {
+        if let Some(stats) = &mut stats {
+            stats.record(&gol)?;
+        }
+        cycles.observe(&gol);
+        if gol.generation >= generations || cycles.behavior().is_some() {
+            break;
+        }
+        gol.update();
+    }
+
+    let population = gol.cells.iter().filter(|c| **c != 0).count();
+    println!(
+        "Generation {}: population {population} ({:.2?})",
+        gol.generation,
+        timer.elapsed()
+    );
+    match cycles.behavior() {
+        Some(behavior) => println!("Behavior: {behavior}"),
+        None => println!("Behavior: no cycle detected"),
+    }
+    Ok(())
+}
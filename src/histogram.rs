@@ -0,0 +1,85 @@
+use crate::{game_of_life::GameOfLife, rule::Rule};
+
+/// Default number of generations the neighbor counts are recorded for.
+pub const DEFAULT_GENERATIONS: u64 = 100;
+
+/// How often each neighbor count occurred over a run.
+///
+/// Only cells in state 1 consult the survive bits and only dead cells
+/// consult the born bits; the other states decay regardless of their
+/// neighbors and are not counted.
+pub struct NeighborHistogram {
+    pub alive: Vec<u64>,
+    pub dead: Vec<u64>,
+}
+
+impl NeighborHistogram {
+    pub fn new(rule: &Rule) -> Self {
+        let len = rule.neighborhood.size() as usize + 1;
+        Self {
+            alive: vec![0; len],
+            dead: vec![0; len],
+        }
+    }
+
+    /// Count the neighbors of every cell of the current generation.
+    pub fn observe(&mut self, gol: &GameOfLife) {
+        for (i, c) in gol.cells.indexed_iter() {
+            let count = gol.rule.count_neighbors(&gol.cells, i) as usize;
+            match c {
+                0 => self.dead[count] += 1,
+                1 => self.alive[count] += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// `rule` with every survive and born bit cleared whose neighbor count
+    /// never occurred. It behaves exactly like `rule` on the recorded run.
+    pub fn minimize(&self, rule: &Rule) -> Rule {
+        let mask = |counts: &[u64]| {
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| **n != 0)
+                .fold(0u32, |acc, (i, _)| acc | 1 << i)
+        };
+        Rule {
+            survive_mask: rule.survive_mask & mask(&self.alive),
+            born_mask: rule.born_mask & mask(&self.dead),
+            ..rule.clone()
+        }
+    }
+}
+
+/// Record the neighbor counts of `gol` over `generations` generations and
+/// print them together with the minimized rule.
+pub fn run(mut gol: GameOfLife, generations: u64) {
+    println!("Rule: {} ({})", gol.rule, gol.rule.analysis());
+    let mut histogram = NeighborHistogram::new(&gol.rule);
+    while gol.generation < generations {
+        histogram.observe(&gol);
+        gol.update();
+    }
+
+    println!(
+        "{:>5} {:>12} {:>4} {:>12} {:>4}",
+        "count", "alive", "S", "dead", "B"
+    );
+    let bit = |set: bool| if set { "on" } else { "" };
+    for (n, (alive, dead)) in
+        histogram.alive.iter().zip(&histogram.dead).enumerate()
+    {
+        println!(
+            "{n:>5} {alive:>12} {:>4} {dead:>12} {:>4}",
+            bit(gol.rule.survive(n as u8)),
+            bit(gol.rule.born(n as u8))
+        );
+    }
+    let minimized = histogram.minimize(&gol.rule);
+    let removed = (gol.rule.survive_mask.count_ones()
+        + gol.rule.born_mask.count_ones())
+        - (minimized.survive_mask.count_ones()
+            + minimized.born_mask.count_ones());
+    println!("Minimized: {minimized} ({removed} bits cleared)");
+}
@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+
+use ndarray::Array3;
+
+use crate::{game_of_life::GameOfLife, run_length};
+
+/// A bounded ring of past generations that can be rewound and scrubbed.
+///
+/// Frames are stored run-length encoded. `cursor` points at the frame that is
+/// currently shown; frames after it are only dropped once the simulation is
+/// resumed from an earlier generation.
+pub struct History {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    cursor: usize,
+}
+
+struct Frame {
+    generation: u64,
+    dim: (usize, usize, usize),
+    data: Vec<u8>,
+}
+
+impl Frame {
+    fn new(generation: u64, cells: &Array3<u8>) -> Self {
+        Self {
+            generation,
+            dim: cells.dim(),
+            data: run_length::encode(cells.iter().copied()),
+        }
+    }
+
+    fn cells(&self) -> Array3<u8> {
+        let len = self.dim.0 * self.dim.1 * self.dim.2;
+        let raw = run_length::decode(&self.data, len)
+            .expect("history frames are always encoded from a full grid");
+        Array3::from_shape_vec(self.dim, raw).unwrap()
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            cursor: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.cursor = 0;
+    }
+
+    /// Record the current generation of `gol`. Any frames after the cursor
+    /// are discarded, since the simulation has diverged from them.
+    pub fn record(&mut self, gol: &GameOfLife) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.truncate();
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames
+            .push_back(Frame::new(gol.generation, &gol.cells));
+        self.cursor = self.frames.len() - 1;
+    }
+
+    /// Move the cursor by `offset` frames (clamped to the recorded range) and
+    /// restore that generation into `gol`. Returns `false` if the cursor did
+    /// not move.
+    pub fn seek(&mut self, offset: isize, gol: &mut GameOfLife) -> bool {
+        if self.frames.is_empty() {
+            return false;
+        }
+        let target = self
+            .cursor
+            .saturating_add_signed(offset)
+            .min(self.frames.len() - 1);
+        if target == self.cursor {
+            return false;
+        }
+        self.cursor = target;
+        let frame = &self.frames[self.cursor];
+        gol.cells = frame.cells();
+        gol.generation = frame.generation;
+        true
+    }
+
+    /// Drop all frames after the cursor, making the shown generation the
+    /// latest one.
+    pub fn truncate(&mut self) {
+        self.frames.truncate(self.cursor + 1);
+    }
+
+    /// Whether the cursor is on an earlier generation than the latest one.
+    pub fn is_rewound(&self) -> bool {
+        self.cursor + 1 < self.frames.len()
+    }
+
+    /// The generation range currently held in the history.
+    pub fn range(&self) -> Option<(u64, u64)> {
+        Some((
+            self.frames.front()?.generation,
+            self.frames.back()?.generation,
+        ))
+    }
+}
@@ -8,6 +8,7 @@ use crate::game_of_life::{GameOfLife, SIZE};
 
 pub struct InstancesVec {
     pub data: Vec<Instance>,
+    #[allow(dead_code)]
     pub raw: Vec<RawInstance>,
     pub buffer: Buffer,
 }
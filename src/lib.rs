@@ -1,48 +1,206 @@
 pub(crate) mod args;
+pub(crate) mod batch;
 pub(crate) mod camera;
+pub(crate) mod census;
+pub(crate) mod classify;
+pub(crate) mod components;
 mod compute_env;
 pub(crate) mod cool_rules;
+pub(crate) mod cycle;
+pub(crate) mod damage;
 pub(crate) mod environment;
+pub(crate) mod evolve;
 pub(crate) mod game_of_life;
+pub(crate) mod headless;
+pub(crate) mod histogram;
+pub(crate) mod history;
 pub(crate) mod instance;
 pub(crate) mod model;
+pub(crate) mod palette;
+pub(crate) mod pattern;
 pub(crate) mod rule;
+pub(crate) mod rule_file;
 pub(crate) mod rule_parse;
+pub(crate) mod run_length;
+pub(crate) mod scene;
+pub(crate) mod search;
+pub(crate) mod sensitivity;
+pub(crate) mod snapshot;
+pub(crate) mod spaceship;
+pub(crate) mod stats;
+pub(crate) mod sweep;
 pub(crate) mod texture;
+pub(crate) mod vox;
 
-use std::fs;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use camera::Camera;
-use clap::Parser;
+use anyhow::Context;
+use camera::{Camera, CameraPose};
 use compute_env::ComputeEnv;
+use cool_rules::Presets;
+use cycle::CycleDetector;
+use damage::DamageOverlay;
 use environment::Environment;
 use game_of_life::{GameOfLife, SIZE};
+use history::History;
+use palette::{Palette, PaletteBinding};
+use pattern::{Pattern, Placement};
+use rule_file::RuleEntry;
+use scene::Scene;
+use stats::StatsWriter;
 
 use model::{Model, Vertex};
 
+use ndarray_rand::rand;
 use pollster::FutureExt;
 use rule::Rule;
 use wgpu::{
-    include_wgsl,
-    util::{BufferInitDescriptor, DeviceExt},
-    BindGroup, BindGroupEntry, BlendState, Buffer, BufferAddress,
-    BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
-    CommandEncoderDescriptor, ComputePassDescriptor, DepthBiasState,
-    DepthStencilState, Device, FragmentState, MultisampleState, Operations,
-    PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
-    PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
-    StencilState, SurfaceConfiguration, TextureViewDescriptor, VertexState,
+    include_wgsl, BlendState, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CommandEncoderDescriptor,
+    ComputePassDescriptor, DepthBiasState, DepthStencilState, Device,
+    FragmentState, MultisampleState, Operations, PipelineLayout,
+    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModule, StencilState,
+    SurfaceConfiguration, TextureViewDescriptor, VertexState,
 };
 use winit::{
     event::{ElementState, VirtualKeyCode, WindowEvent},
     window::Window,
 };
 
+pub use args::{Args, Command};
+
 const WORKGROUP_SIZE: u32 = 4;
+/// Number of generations skipped when scrubbing through the history.
+const SCRUB_STEP: isize = 10;
+/// Directory favorite rules are saved to.
+const FAVORITES_DIR: &str = "cool_rules";
+/// Directory scenes are saved to.
+const SCENES_DIR: &str = "scenes";
+/// Directory snapshots are saved to.
+const SNAPSHOTS_DIR: &str = "snapshots";
+/// Directory patterns are saved to.
+const PATTERNS_DIR: &str = "patterns";
+/// Most generations simulated per frame at a fixed `--speed`.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// A length or coordinate along the grid, either in cells or relative to the
+/// edge length of the grid. Relative ones remember whether they were written
+/// as percent or as a fraction.
+#[derive(
+    Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "ExtentRepr", into = "ExtentRepr")]
+pub enum Extent {
+    Cells(usize),
+    /// `50%`
+    Percent(f64),
+    /// `0.5`, always written with a decimal point.
+    Fraction(f64),
+}
+
+impl Extent {
+    /// The number of cells on a grid with edge length `grid_size`.
+    pub fn resolve(&self, grid_size: usize) -> usize {
+        match self {
+            Extent::Cells(n) => *n,
+            Extent::Percent(p) => {
+                (p / 100. * grid_size as f64).round() as usize
+            }
+            Extent::Fraction(f) => (f * grid_size as f64).round() as usize,
+        }
+    }
+}
+
+impl std::fmt::Display for Extent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Extent::Cells(n) => f.pad(&n.to_string()),
+            Extent::Percent(p) => f.pad(&format!("{p}%")),
+            Extent::Fraction(x) => f.pad(&format!("{x:?}")),
+        }
+    }
+}
+
+impl std::str::FromStr for Extent {
+    type Err = rule_parse::ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        rule_parse::extent(s)
+    }
+}
+
+/// How an [`Extent`] is stored in scene files: cells as integers, fractions
+/// as floats and percentages as strings like `"50%"`.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum ExtentRepr {
+    Cells(usize),
+    Fraction(f64),
+    Text(String),
+}
+
+impl TryFrom<ExtentRepr> for Extent {
+    type Error = rule_parse::ParseRuleError;
+
+    fn try_from(repr: ExtentRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ExtentRepr::Cells(n) => Ok(Extent::Cells(n)),
+            ExtentRepr::Fraction(f) => Ok(Extent::Fraction(f)),
+            ExtentRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Extent> for ExtentRepr {
+    fn from(extent: Extent) -> Self {
+        match extent {
+            Extent::Cells(n) => ExtentRepr::Cells(n),
+            Extent::Fraction(f) => ExtentRepr::Fraction(f),
+            Extent::Percent(_) => ExtentRepr::Text(extent.to_string()),
+        }
+    }
+}
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Init {
-    pub size: usize,
+    /// Edge length of the initial cube.
+    pub size: Extent,
     pub density: f64,
+    pub seed: Option<u64>,
+    /// Center of the initial cube [default: the center of the grid].
+    pub position: Option<[Extent; 3]>,
+}
+
+impl Default for Init {
+    fn default() -> Self {
+        Self {
+            size: Extent::Cells(10),
+            density: 0.5,
+            seed: None,
+            position: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Init {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.size)?;
+        if let Some([x, y, z]) = &self.position {
+            write!(f, "@{x},{y},{z}")?;
+        }
+        write!(f, "/{}", self.density)?;
+        if let Some(seed) = self.seed {
+            write!(f, "/{seed}")?;
+        }
+        Ok(())
+    }
 }
 
 pub struct State {
@@ -56,45 +214,267 @@ pub struct State {
     paused: bool,
     cursor_grab: bool,
     compute_env: ComputeEnv,
+    history: History,
+    cycles: CycleDetector,
+    stats: Option<StatsWriter>,
+    /// Seed of the current soup.
+    soup_seed: u64,
+    /// Earlier rules and their soup seeds, most recent last.
+    rule_undo: Vec<(Rule, u64)>,
+    damage: Option<DamageOverlay>,
+    palette: Option<Palette>,
+    palette_binding: PaletteBinding,
+    background: Option<[u8; 3]>,
+    /// Generations per second, or one per frame.
+    speed: Option<f32>,
+    /// Time since the last generation at a fixed speed.
+    sim_time: f32,
+    presets: Presets,
+    /// Index of the current preset, if the rule is one.
+    preset: Option<usize>,
+    /// Pattern that replaces the random soup.
+    placement: Option<Placement>,
+    /// Whether the grid was loaded from a snapshot instead of starting from
+    /// the soup or pattern.
+    from_snapshot: bool,
+    /// The window title last set.
+    title: String,
 }
-impl State {
-    pub fn new(window: Window) -> Self {
-        //* GOL
 
-        let args = args::Args::parse();
+/// Print the presets given by the arguments.
+pub fn list_presets(args: &Args) -> anyhow::Result<()> {
+    cool_rules::list(&args.presets()?);
+    Ok(())
+}
 
-        let (rule, mut init) = {
-            let mut rule_string = if let Some(r) = args.rule {
-                r
-            } else if let Some(f) = args.file {
-                fs::read_to_string(f).unwrap()
-            } else {
-                cool_rules::as_str::PERIODIC_FUNKY.to_string()
-            };
+/// Run the simulation for `generations` generations without a window.
+pub fn run_headless(args: &Args, generations: u64) -> anyhow::Result<()> {
+    headless::run(args, generations)
+}
+
+/// Run one of the headless tools.
+pub fn run_command(args: &Args, command: &Command) -> anyhow::Result<()> {
+    match command {
+        Command::Gliders {
+            generations,
+            max_period,
+        } => {
+            spaceship::run(
+                GameOfLife::try_from(args)?,
+                *generations,
+                *max_period,
+            );
+        }
+        Command::Census {
+            generations,
+            connectivity,
+            output,
+        } => census::run(
+            GameOfLife::try_from(args)?,
+            *generations,
+            *connectivity,
+            output.as_deref(),
+        )?,
+        Command::Search {
+            count,
+            generations,
+            keep,
+            lambda,
+            output,
+        } => search::run(
+            args,
+            *count,
+            *generations,
+            keep,
+            lambda.as_deref().map(|l| l[0]..=l[1]).as_ref(),
+            output,
+        )?,
+        Command::Sensitivity { generations } => {
+            sensitivity::run(args, *generations)?
+        }
+        Command::Minimize { generations } => {
+            histogram::run(GameOfLife::try_from(args)?, *generations);
+        }
+        Command::Damage {
+            generations,
+            cell,
+            output,
+        } => damage::run(
+            GameOfLife::try_from(args)?,
+            *generations,
+            cell.as_deref(),
+            output.as_deref(),
+        )?,
+        Command::Crop {
+            generations,
+            min,
+            size,
+            output,
+        } => pattern::run(
+            GameOfLife::try_from(args)?,
+            *generations,
+            min.as_deref().zip(size.as_deref()),
+            output.as_deref(),
+            args.rule_entry()?.palette.as_ref(),
+        )?,
+        Command::Normalize { files, notation } => {
+            rule::normalize(args, files, *notation)?
+        }
+        Command::Sweep {
+            sizes,
+            densities,
+            seeds,
+            generations,
+            output,
+        } => sweep::run(
+            args,
+            sizes,
+            densities,
+            *seeds,
+            *generations,
+            output.as_deref(),
+        )?,
+        Command::Batch {
+            path,
+            generations,
+            output,
+        } => batch::run(args, path, *generations, output.as_deref())?,
+        Command::Evolve {
+            fitness,
+            target,
+            population,
+            epochs,
+            generations,
+            checkpoint,
+            best,
+            output,
+        } => evolve::run(
+            args,
+            *fitness,
+            *target,
+            *population,
+            *epochs,
+            *generations,
+            checkpoint.as_deref(),
+            *best,
+            output,
+        )?,
+    }
+    Ok(())
+}
 
-            rule_string.retain(|c| !c.is_whitespace());
+/// A new file in `dir` named after `prefix` and the current time, creating
+/// `dir` if needed.
+fn timestamped_path(
+    dir: &str,
+    prefix: &str,
+    extension: &str,
+) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    fs::create_dir_all(dir)?;
+    Ok(Path::new(dir).join(format!("{prefix}_{secs}.{extension}")))
+}
 
-            rule_parse::rule_and_init(&rule_string).unwrap().1
-        };
+impl State {
+    pub fn new(window: Window, args: Args) -> anyhow::Result<Self> {
+        //* GOL
 
-        if let Some(s) = args.init_size {
-            init.size = s;
+        if args.grid_size.is_some() {
+            log::warn!("--grid-size only applies to the headless tools");
         }
-        if let Some(d) = args.init_density {
-            init.density = d;
+        let scene = args.scene()?;
+        if let Some(scene) = &scene {
+            if scene.grid.size != SIZE {
+                anyhow::bail!(
+                    "the viewer only supports a grid size of {SIZE}, not {}",
+                    scene.grid.size
+                );
+            }
         }
-
-        let gol = GameOfLife {
-            cells: GameOfLife::cells_random_init(rule.max_state, &init),
-            rule,
-            init,
+        let entry = args.rule_entry()?;
+        let presets = args.presets().unwrap_or_else(|e| {
+            log::error!("Could not load all presets: {e:#}");
+            Presets::embedded()
+        });
+        let preset = presets.position(&entry.name);
+        let placement = args.placement()?;
+        let (rule, init) = (entry.rule.clone(), entry.init.clone());
+        // Remember the seed of every soup, so that favorites can be saved
+        // with it even if no seed was given.
+        let soup_seed = init.seed.unwrap_or_else(rand::random);
+        let mut gol = match &args.load {
+            Some(path) => {
+                let gol = GameOfLife::load(path)?;
+                if gol.cells.dim() != (SIZE, SIZE, SIZE) {
+                    anyhow::bail!(
+                        "the viewer only supports a grid size of {SIZE}, not \
+                         {:?}",
+                        gol.cells.dim()
+                    );
+                }
+                gol
+            }
+            None => match &placement {
+                Some(placement) => GameOfLife {
+                    cells: placement.cells(SIZE, rule.max_state),
+                    rule,
+                    init: init.clone(),
+                    generation: 0,
+                },
+                None => GameOfLife::new(
+                    rule,
+                    Init {
+                        seed: Some(soup_seed),
+                        ..init.clone()
+                    },
+                    SIZE,
+                )?,
+            },
         };
+        gol.init = init;
+        if let Some(scene) = &scene {
+            let generations = scene.simulation.generation;
+            if generations > 0 {
+                println!("Simulating {generations} generations of the scene");
+            }
+            while gol.generation < generations {
+                gol.update();
+            }
+        }
+        let mut history = History::new(args.history);
+        history.record(&gol);
+        let mut cycles = CycleDetector::new(args.cycle_window.unwrap_or(0));
+        cycles.observe(&gol);
+        let mut stats = args.stats.as_ref().and_then(|path| {
+            StatsWriter::create(path)
+                .map_err(|e| {
+                    log::error!("Could not create {}: {e}", path.display())
+                })
+                .ok()
+        });
+        if let Some(stats) = &mut stats {
+            if let Err(e) = stats.record(&gol) {
+                log::error!("Could not write statistics: {e}");
+            }
+        }
         //* ENVIRONMENT
         let env = Environment::new(window).block_on();
 
         //* CAMERA
-        let (camera, camera_bind_group_layout) =
+        let (mut camera, camera_bind_group_layout) =
             Camera::create_camera(&env.device, &env.config);
+        if let Some(pose) = entry.camera {
+            camera.set_pose(pose);
+        }
+        if let Some(scene_camera) =
+            scene.as_ref().and_then(|s| s.camera.as_ref())
+        {
+            camera.entity.fovy = scene_camera.fovy;
+            camera.uniform.update_view_proj(&camera.entity);
+        }
 
         //* MODEL
         let model = Model::new(&env.device, model::CUBE, model::CUBE_INDICES);
@@ -103,13 +483,21 @@ impl State {
         //* RENDERING
         let depth_texture =
             texture::Texture::create_depth_texture(&env.device, &env.config);
+        let palette_binding = PaletteBinding::new(
+            &env.device,
+            entry.palette.as_ref(),
+            gol.rule.max_state,
+        );
         let draw_shader =
             env.device.create_shader_module(include_wgsl!("draw.wgsl"));
         let render_pipeline_layout =
             env.device
                 .create_pipeline_layout(&PipelineLayoutDescriptor {
                     label: Some("Render Pipeline Layout"),
-                    bind_group_layouts: &[&camera_bind_group_layout],
+                    bind_group_layouts: &[
+                        &camera_bind_group_layout,
+                        &palette_binding.bind_group_layout,
+                    ],
                     push_constant_ranges: &[],
                 });
         let render_pipeline = Self::generate_render_pipeline(
@@ -121,7 +509,7 @@ impl State {
 
         let compute_env = ComputeEnv::new(&gol, &env.device, &instances);
 
-        Self {
+        Ok(Self {
             env,
             camera,
             model,
@@ -129,10 +517,34 @@ impl State {
             depth_texture,
             render_pipeline,
             gol,
-            paused: true,
+            paused: scene.as_ref().is_none_or(|s| s.simulation.paused),
             cursor_grab: false,
             compute_env,
-        }
+            history,
+            cycles,
+            stats,
+            soup_seed,
+            rule_undo: match &scene {
+                Some(scene) => scene.undo_rules()?,
+                None => Vec::new(),
+            },
+            damage: None,
+            palette: entry.palette,
+            palette_binding,
+            background: match &scene {
+                Some(scene) => scene.background()?,
+                None => None,
+            },
+            speed: args
+                .speed
+                .or(scene.as_ref().and_then(|s| s.simulation.speed)),
+            sim_time: 0.,
+            presets,
+            preset,
+            placement,
+            from_snapshot: args.load.is_some(),
+            title: String::new(),
+        })
     }
     fn generate_render_pipeline(
         device: &Device,
@@ -186,93 +598,345 @@ impl State {
         self.instances =
             instance::InstancesVec::from((&self.gol, &self.env.device));
         self.compute_env.num_instances = self.instances.data.len() as u32;
+        self.compute_env.step_toggle = 0;
         (
             self.compute_env.bind_groups,
+            self.compute_env.cells_buffers,
             self.compute_env.atomic_counter_buffer,
-        ) = State::generate_cells_buffers_bind_group(self);
-    }
-    fn generate_cells_buffers_bind_group(&self) -> ([BindGroup; 2], Buffer) {
-        let cells_vec: Vec<u32> = self
-            .gol
-            .cells
-            .clone()
-            .into_raw_vec()
-            .iter()
-            .map(|x| *x as u32)
-            .collect();
-
-        let mut buffers = Vec::with_capacity(2);
-        for _i in 0..=1 {
-            buffers.push(self.env.device.create_buffer_init(
-                &BufferInitDescriptor {
-                    label: Some("Cells Buffer {i}"),
-                    contents: bytemuck::cast_slice(&cells_vec),
-                    usage: BufferUsages::STORAGE,
-                },
-            ));
-        }
-        let atomic_counter_buffer =
-            self.env.device.create_buffer_init(&BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::bytes_of(
-                    &(self.instances.data.len() as u32),
-                ),
-                usage: BufferUsages::STORAGE
-                    | BufferUsages::COPY_SRC
-                    | BufferUsages::COPY_DST,
-            });
-        let bind_groups = (0..=1)
-            .map(|i| {
-                self.env
-                    .device
-                    .create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("Compute Bind Group {i}"),
-                        layout: &self.compute_env.bind_groups_layout,
-                        entries: &[
-                            BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::Buffer(
-                                    self.gol
-                                        .rule
-                                        .as_buffer(&self.env.device)
-                                        .as_entire_buffer_binding(),
-                                ),
-                            },
-                            BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Buffer(
-                                    buffers[i].as_entire_buffer_binding(),
-                                ),
-                            },
-                            BindGroupEntry {
-                                binding: 2,
-                                resource: wgpu::BindingResource::Buffer(
-                                    buffers[(i + 1) % 2]
-                                        .as_entire_buffer_binding(),
-                                ),
-                            },
-                            BindGroupEntry {
-                                binding: 3,
-                                resource: wgpu::BindingResource::Buffer(
-                                    self.instances
-                                        .buffer
-                                        .as_entire_buffer_binding(),
-                                ),
-                            },
-                            BindGroupEntry {
-                                binding: 4,
-                                resource: wgpu::BindingResource::Buffer(
-                                    atomic_counter_buffer
-                                        .as_entire_buffer_binding(),
-                                ),
-                            },
-                        ],
-                    })
-            })
-            .collect::<Vec<BindGroup>>()
-            .try_into()
-            .unwrap();
-        (bind_groups, atomic_counter_buffer)
+        ) = ComputeEnv::generate_cells_buffers_bind_group(
+            &self.gol.cells,
+            &self.env.device,
+            &self.compute_env.bind_groups_layout,
+            &self.instances,
+            &self.gol.rule,
+        );
+        self.palette_binding.update(
+            &self.env.queue,
+            self.palette.as_ref(),
+            self.gol.rule.max_state,
+        );
+    }
+
+    /// Copy the current generation from the GPU into `self.gol.cells`.
+    pub fn sync_cells(&mut self) {
+        self.gol.cells = self.compute_env.read_cells(
+            &self.env.device,
+            &self.env.queue,
+            self.gol.cells.dim(),
+        );
+    }
+
+    /// Start over from a fresh initial state, forgetting the history.
+    fn reset_cells(&mut self, cells: ndarray::Array3<u8>) {
+        self.from_snapshot = false;
+        self.gol.cells = cells;
+        self.gol.generation = 0;
+        self.restart();
+    }
+
+    /// Upload the current generation and start recording history and
+    /// statistics from it.
+    fn restart(&mut self) {
+        self.damage = None;
+        self.update_cells_buffers();
+        self.history.clear();
+        self.history.record(&self.gol);
+        self.cycles.clear();
+        self.cycles.observe(&self.gol);
+        if let Some(stats) = &mut self.stats {
+            stats.restart();
+        }
+        self.record_stats();
+        self.update_title();
+    }
+
+    /// A new soup from the initial cube, seeded with `--seed` or, if none was
+    /// given, a fresh seed.
+    fn random_cells(&mut self) -> anyhow::Result<ndarray::Array3<u8>> {
+        self.soup_seed = self.gol.init.seed.unwrap_or_else(rand::random);
+        self.initial_cells(self.soup_seed)
+    }
+
+    /// Start over from a new soup, see [`State::random_cells`].
+    fn reset_soup(&mut self) {
+        match self.random_cells() {
+            Ok(cells) => self.reset_cells(cells),
+            Err(e) => log::error!("Could not create the soup: {e:#}"),
+        }
+    }
+
+    /// The soup with `seed`, or the pattern if there is one.
+    fn initial_cells(&self, seed: u64) -> anyhow::Result<ndarray::Array3<u8>> {
+        if let Some(placement) = &self.placement {
+            return Ok(placement.cells(SIZE, self.gol.rule.max_state));
+        }
+        GameOfLife::cells_random_init(
+            SIZE,
+            self.gol.rule.max_state,
+            &Init {
+                seed: Some(seed),
+                ..self.gol.init.clone()
+            },
+        )
+    }
+
+    /// Switch to `rule` on a new soup, remembering the current rule for
+    /// [`State::undo_rule`].
+    fn change_rule(&mut self, rule: Rule) {
+        self.rule_undo.push((self.gol.rule.clone(), self.soup_seed));
+        self.gol.rule = rule;
+        self.reset_soup();
+        println!("{} ({})", self.gol.rule, self.gol.rule.analysis());
+    }
+
+    /// Go back to the previous rule and its soup.
+    fn undo_rule(&mut self) {
+        let Some((rule, seed)) = self.rule_undo.pop() else {
+            return;
+        };
+        self.gol.rule = rule;
+        self.soup_seed = seed;
+        match self.initial_cells(seed) {
+            Ok(cells) => self.reset_cells(cells),
+            Err(e) => log::error!("Could not create the soup: {e:#}"),
+        }
+        println!("{} ({})", self.gol.rule, self.gol.rule.analysis());
+    }
+
+    /// Switch to the preset `offset` places after the current one, or the
+    /// first one.
+    fn cycle_preset(&mut self, offset: isize) {
+        let len = self.presets.entries().len() as isize;
+        let index = match self.preset {
+            Some(i) => (i as isize + offset).rem_euclid(len) as usize,
+            None => 0,
+        };
+        self.preset = Some(index);
+        let entry = self.presets.entries()[index].clone();
+        println!(
+            "Preset {}: {}",
+            entry.name,
+            entry.description.as_deref().unwrap_or("-")
+        );
+        self.gol.init = entry.init;
+        self.palette = entry.palette;
+        if let Some(pose) = entry.camera {
+            self.camera.set_pose(pose);
+        }
+        self.change_rule(entry.rule);
+    }
+
+    /// Switch to 2D Life on the board, with the current pattern if it is 2D
+    /// or else a glider.
+    fn play_2d(&mut self) {
+        self.placement = Some(match self.placement.take() {
+            Some(placement) if placement.board => placement,
+            Some(placement) if placement.pattern.cells.dim().2 == 1 => {
+                Placement {
+                    board: true,
+                    ..placement
+                }
+            }
+            _ => Placement::glider_board(),
+        });
+        self.change_rule(game_of_life::GOL_2D_RULE.parse().unwrap());
+    }
+
+    /// Write the current rule, initial cube, soup seed, camera pose and
+    /// palette to a new file in `cool_rules/`.
+    fn save_favorite(&self) -> io::Result<PathBuf> {
+        let init = Init {
+            seed: Some(self.soup_seed),
+            ..self.gol.init.clone()
+        };
+        let path = timestamped_path(FAVORITES_DIR, "favorite", "gol3d")?;
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let entry = RuleEntry {
+            camera: Some(CameraPose {
+                pos: self.camera.entity.pos,
+                dir: self.camera.entity.dir,
+            }),
+            palette: self.palette.clone(),
+            ..RuleEntry::new(&name, self.gol.rule.clone(), init)
+        };
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        write!(file, "{entry}")?;
+        Ok(path)
+    }
+
+    /// The current state as a scene, from which it can be reproduced.
+    fn scene(&self) -> Scene {
+        let rule_and_init = |rule: &Rule, seed: u64| {
+            let init = Init {
+                seed: Some(seed),
+                ..self.gol.init.clone()
+            };
+            format!("{rule}/{init}")
+        };
+        let entity = &self.camera.entity;
+        Scene {
+            rule: rule_and_init(&self.gol.rule, self.soup_seed),
+            undo: self
+                .rule_undo
+                .iter()
+                .map(|(rule, seed)| rule_and_init(rule, *seed))
+                .collect(),
+            grid: scene::Grid {
+                size: self.gol.cells.dim().0,
+                boundary: Some(if self.gol.rule.neighborhood.is_wrapping() {
+                    scene::Boundary::Wrap
+                } else {
+                    scene::Boundary::Fixed
+                }),
+            },
+            init: None,
+            camera: Some(scene::SceneCamera {
+                position: entity.pos.into(),
+                direction: entity.dir.into(),
+                fovy: entity.fovy,
+            }),
+            render: scene::Render {
+                palette: self.palette.as_ref().map(|palette| {
+                    palette
+                        .0
+                        .iter()
+                        .copied()
+                        .map(palette::format_color)
+                        .collect()
+                }),
+                background: self.background.map(palette::format_color),
+            },
+            simulation: scene::Simulation {
+                speed: self.speed,
+                generation: self.gol.generation,
+                paused: self.paused,
+            },
+        }
+    }
+
+    /// Write the current state to a new scene file in `scenes/`.
+    fn save_scene(&self) -> anyhow::Result<PathBuf> {
+        // A scene only holds the rule and soup the grid is simulated from.
+        if self.placement.is_some() || self.from_snapshot {
+            anyhow::bail!(
+                "scenes start from a random soup, save a pattern (P) or \
+                 snapshot (F5) of this grid instead"
+            );
+        }
+        let path = timestamped_path(SCENES_DIR, "scene", "toml")?;
+        self.scene().save(&path)?;
+        Ok(path)
+    }
+
+    /// Read the current generation back from the GPU and write it to a new
+    /// snapshot in `snapshots/`.
+    fn save_snapshot(&mut self) -> anyhow::Result<PathBuf> {
+        self.sync_cells();
+        let gol = GameOfLife {
+            init: Init {
+                seed: Some(self.soup_seed),
+                ..self.gol.init.clone()
+            },
+            ..self.gol.clone()
+        };
+        let path =
+            timestamped_path(SNAPSHOTS_DIR, "snapshot", snapshot::EXTENSION)?;
+        gol.save(&path)?;
+        Ok(path)
+    }
+
+    /// Write the bounding box of the live cells of the current generation to
+    /// a new pattern file in `patterns/`, as RLE or as MagicaVoxel file with
+    /// the extension `vox::EXTENSION`.
+    fn save_pattern(&mut self, extension: &str) -> anyhow::Result<PathBuf> {
+        self.sync_cells();
+        let pattern =
+            Pattern::crop_live(&self.gol).context("there are no live cells")?;
+        let path = timestamped_path(PATTERNS_DIR, "pattern", extension)?;
+        if extension == vox::EXTENSION {
+            vox::write(&pattern, self.palette.as_ref(), &path)?;
+        } else {
+            pattern.write(Some(&path))?;
+        }
+        Ok(path)
+    }
+
+    /// Continue from the most recent snapshot in `snapshots/`, remembering
+    /// the current rule for [`State::undo_rule`].
+    fn load_snapshot(&mut self) -> anyhow::Result<PathBuf> {
+        let path = fs::read_dir(SNAPSHOTS_DIR)
+            .with_context(|| format!("could not read {SNAPSHOTS_DIR}"))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|p| p.extension().is_some_and(|e| e == snapshot::EXTENSION))
+            .max()
+            .with_context(|| format!("no snapshots in {SNAPSHOTS_DIR}"))?;
+        let gol = GameOfLife::load(&path)?;
+        if gol.cells.dim() != (SIZE, SIZE, SIZE) {
+            anyhow::bail!("the snapshot does not have a grid size of {SIZE}");
+        }
+        self.rule_undo.push((self.gol.rule.clone(), self.soup_seed));
+        self.soup_seed = gol.init.seed.unwrap_or(self.soup_seed);
+        self.gol = gol;
+        self.from_snapshot = true;
+        self.paused = true;
+        self.restart();
+        Ok(path)
+    }
+
+    /// Move through the recorded history and upload that generation.
+    fn seek_history(&mut self, offset: isize) {
+        if !self.history.is_enabled() {
+            log::warn!("No history is kept, run with --history to enable it");
+            return;
+        }
+        self.paused = true;
+        if self.history.seek(offset, &mut self.gol) {
+            self.damage = None;
+            self.update_cells_buffers();
+            self.cycles.clear();
+            if let Some(stats) = &mut self.stats {
+                stats.restart();
+            }
+            self.update_title();
+        }
+    }
+
+    fn record_stats(&mut self) {
+        if let Some(stats) = &mut self.stats {
+            if let Err(e) = stats.record(&self.gol) {
+                log::error!("Could not write statistics, stopping: {e}");
+                self.stats = None;
+            }
+        }
+    }
+
+    /// Set the window title, unless it has not changed.
+    pub fn update_title(&mut self) {
+        let mut title =
+            format!("Rule: {} | Gen: {}", self.gol.rule, self.gol.generation);
+        if let Some((first, last)) =
+            self.history.range().filter(|_| self.history.is_rewound())
+        {
+            title += &format!(" (history {first}-{last})");
+        }
+        if let Some(behavior) = self.cycles.behavior() {
+            title += &format!(" | {behavior}");
+        }
+        if let Some(overlay) = &self.damage {
+            title += &format!(
+                " | damage: {} cells, extent {}",
+                overlay.stats.hamming, overlay.stats.extent
+            );
+        }
+        if title != self.title {
+            self.env.window.set_title(&title);
+            self.title = title;
+        }
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -311,41 +975,173 @@ impl State {
                 if input.virtual_keycode == Some(VirtualKeyCode::R)
                     && input.state == ElementState::Released =>
             {
-                self.gol.cells = GameOfLife::cells_random_init(
-                    self.gol.rule.max_state,
-                    &self.gol.init,
-                );
-                self.update_cells_buffers();
+                self.reset_soup();
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::Back)
+                    && input.state == ElementState::Released =>
+            {
+                self.seek_history(-1);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::PageUp)
+                    && input.state == ElementState::Released =>
+            {
+                self.seek_history(-SCRUB_STEP);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::PageDown)
+                    && input.state == ElementState::Released =>
+            {
+                self.seek_history(SCRUB_STEP);
                 return true;
             }
             WindowEvent::KeyboardInput { input, .. }
                 if input.virtual_keycode == Some(VirtualKeyCode::B)
                     && input.state == ElementState::Released =>
             {
-                self.gol.rule = "1-8,11-12,17-31/12/2/M/100/1".parse().unwrap();
-                self.gol.cells = GameOfLife::gol_2d_board(
-                    SIZE,
-                    SIZE,
-                    1.,
-                    self.gol.rule.max_state,
-                );
-                self.update_cells_buffers();
+                self.play_2d();
                 return true;
             }
             WindowEvent::KeyboardInput { input, .. }
                 if input.virtual_keycode == Some(VirtualKeyCode::Q)
                     && input.state == ElementState::Released =>
             {
-                self.gol.rule = Rule::new_random();
-                self.gol.cells = GameOfLife::cells_random_init(
-                    self.gol.rule.max_state,
-                    &self.gol.init,
-                );
-                self.update_cells_buffers();
-                println!("{}", self.gol.rule);
-                self.env
-                    .window
-                    .set_title(&format!("Rule: {}", self.gol.rule));
+                self.change_rule(Rule::new_random());
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::M)
+                    && input.state == ElementState::Released =>
+            {
+                let mut rule = self.gol.rule.clone();
+                rule.flip_random_bit(&mut rand::thread_rng());
+                self.change_rule(rule);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::Equals)
+                    && input.state == ElementState::Released =>
+            {
+                let mut rule = self.gol.rule.clone();
+                rule.change_states(1);
+                self.change_rule(rule);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::Minus)
+                    && input.state == ElementState::Released =>
+            {
+                let mut rule = self.gol.rule.clone();
+                rule.change_states(-1);
+                self.change_rule(rule);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::N)
+                    && input.state == ElementState::Released =>
+            {
+                let mut rule = self.gol.rule.clone();
+                rule.neighborhood = rule.neighborhood.next();
+                self.change_rule(rule);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::U)
+                    && input.state == ElementState::Released =>
+            {
+                self.undo_rule();
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::LBracket)
+                    && input.state == ElementState::Released =>
+            {
+                self.cycle_preset(-1);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::RBracket)
+                    && input.state == ElementState::Released =>
+            {
+                self.cycle_preset(1);
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::F)
+                    && input.state == ElementState::Released =>
+            {
+                match self.save_favorite() {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(e) => log::error!("Could not save favorite: {e}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::C)
+                    && input.state == ElementState::Released =>
+            {
+                match self.save_scene() {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(e) => log::error!("Could not save scene: {e:#}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::F5)
+                    && input.state == ElementState::Released =>
+            {
+                match self.save_snapshot() {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(e) => log::error!("Could not save snapshot: {e:#}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::F9)
+                    && input.state == ElementState::Released =>
+            {
+                match self.load_snapshot() {
+                    Ok(path) => println!("Loaded {}", path.display()),
+                    Err(e) => log::error!("Could not load snapshot: {e:#}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::P)
+                    && input.state == ElementState::Released =>
+            {
+                match self.save_pattern(pattern::EXTENSION) {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(e) => log::error!("Could not save pattern: {e:#}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::V)
+                    && input.state == ElementState::Released =>
+            {
+                match self.save_pattern(vox::EXTENSION) {
+                    Ok(path) => println!("Saved {}", path.display()),
+                    Err(e) => log::error!("Could not save voxels: {e:#}"),
+                }
+                return true;
+            }
+            WindowEvent::KeyboardInput { input, .. }
+                if input.virtual_keycode == Some(VirtualKeyCode::X)
+                    && input.state == ElementState::Released =>
+            {
+                self.damage = match self.damage {
+                    Some(_) => None,
+                    None => {
+                        self.sync_cells();
+                        Some(DamageOverlay::new(&self.gol, &self.env.device))
+                    }
+                };
+                self.update_title();
                 return true;
             }
             WindowEvent::KeyboardInput { input, .. }
@@ -384,12 +1180,25 @@ impl State {
                 bytemuck::cast_slice(&[self.camera.uniform.view_proj]),
             );
         }
-        if self.paused {
-            self.render_call();
-        } else {
-            self.update_game_call();
-            self.render_call();
+        if !self.paused {
+            match self.speed {
+                Some(speed) => {
+                    self.sim_time += delta;
+                    let mut steps = 0;
+                    while self.sim_time * speed >= 1. {
+                        self.update_game_call();
+                        self.sim_time -= 1. / speed;
+                        steps += 1;
+                        if steps == MAX_STEPS_PER_FRAME {
+                            // Too slow to keep up, don't try to catch up.
+                            self.sim_time = 0.;
+                        }
+                    }
+                }
+                None => self.update_game_call(),
+            }
         }
+        self.render_call();
     }
 
     fn update_game_call(&mut self) {
@@ -446,6 +1255,26 @@ impl State {
         let res: Vec<u32> = bytemuck::cast_slice(&data).to_vec();
         self.compute_env.num_instances = res[0];
         self.compute_env.step_toggle = (self.compute_env.step_toggle + 1) % 2;
+        self.gol.generation += 1;
+
+        if self.history.is_enabled()
+            || self.cycles.is_enabled()
+            || self.stats.is_some()
+            || self.damage.is_some()
+        {
+            self.sync_cells();
+            self.history.record(&self.gol);
+            if self.cycles.behavior().is_none() {
+                if let Some(behavior) = self.cycles.observe(&self.gol) {
+                    log::info!("Rule {}: {behavior}", self.gol.rule);
+                }
+            }
+            self.record_stats();
+            if let Some(overlay) = &mut self.damage {
+                overlay.step(&self.gol, &self.env.device);
+            }
+        }
+        self.update_title();
     }
 
     fn render_call(&mut self) {
@@ -469,12 +1298,22 @@ impl State {
                             view: &view,
                             resolve_target: None,
                             ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color {
-                                    r: 0.1,
-                                    g: 0.2,
-                                    b: 0.3,
-                                    a: 1.0,
-                                }),
+                                load: wgpu::LoadOp::Clear(
+                                    match self.background {
+                                        Some([r, g, b]) => wgpu::Color {
+                                            r: r as f64 / 255.,
+                                            g: g as f64 / 255.,
+                                            b: b as f64 / 255.,
+                                            a: 1.0,
+                                        },
+                                        None => wgpu::Color {
+                                            r: 0.1,
+                                            g: 0.2,
+                                            b: 0.3,
+                                            a: 1.0,
+                                        },
+                                    },
+                                ),
                                 store: true,
                             },
                         },
@@ -493,6 +1332,11 @@ impl State {
             render_pass.set_pipeline(&self.render_pipeline);
 
             render_pass.set_bind_group(0, &self.camera.bind_group, &[]);
+            render_pass.set_bind_group(
+                1,
+                &self.palette_binding.bind_group,
+                &[],
+            );
             render_pass
                 .set_vertex_buffer(0, self.model.vertex_buffer.slice(..));
             render_pass.set_vertex_buffer(1, self.instances.buffer.slice(..));
@@ -507,6 +1351,14 @@ impl State {
                 0,
                 0..self.compute_env.num_instances,
             );
+            if let Some(overlay) = &self.damage {
+                render_pass.set_vertex_buffer(1, overlay.buffer.slice(..));
+                render_pass.draw_indexed(
+                    0..self.model.num_indices,
+                    0,
+                    0..overlay.num_instances,
+                );
+            }
         }
 
         self.env.queue.submit(Some(encoder.finish()));
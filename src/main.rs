@@ -1,4 +1,5 @@
-use gol_3d::State;
+use clap::Parser;
+use gol_3d::{Args, State};
 
 use std::{collections::VecDeque, time::Instant};
 
@@ -9,11 +10,23 @@ use winit::{
 };
 
 const MOVING_AVERAGE_NUM: usize = 10;
-fn main() {
+#[allow(clippy::collapsible_match)]
+fn main() -> anyhow::Result<()> {
     let mut timer = Instant::now();
     let mut moving_average = VecDeque::from([0.; MOVING_AVERAGE_NUM]);
 
     env_logger::init();
+    let args = Args::parse();
+    if args.list_presets {
+        return gol_3d::list_presets(&args);
+    }
+    if let Some(command) = &args.command {
+        return gol_3d::run_command(&args, command);
+    }
+    if let Some(generations) = args.headless {
+        return gol_3d::run_headless(&args, generations);
+    }
+
     let event_loop = EventLoop::new();
 
     let window = WindowBuilder::new()
@@ -21,11 +34,8 @@ fn main() {
         .build(&event_loop)
         .unwrap();
 
-    let mut state = State::new(window);
-    state
-        .env
-        .window
-        .set_title(&format!("Rule: {}", state.gol.rule));
+    let mut state = State::new(window, args)?;
+    state.update_title();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
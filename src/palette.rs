@@ -0,0 +1,167 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutEntry, Buffer, BufferUsages, Device, Queue, ShaderStages,
+};
+
+/// Most colors a palette can have, see `draw.wgsl`.
+pub const MAX_COLORS: usize = 8;
+
+/// Colors the cells are drawn with, from newborn cells to dying ones. The
+/// states in between are interpolated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette(pub Vec<[u8; 3]>);
+
+/// Parse a hex color like `#ffcc00`.
+pub fn parse_color(color: &str) -> anyhow::Result<[u8; 3]> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("expected a color like #ffcc00, not {color:?}");
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+pub fn format_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// The color of cells in `state`, as drawn by `draw.wgsl`.
+pub fn state_color(
+    palette: Option<&Palette>,
+    state: u8,
+    max_state: u8,
+) -> [u8; 3] {
+    match palette {
+        Some(Palette(colors)) if !colors.is_empty() => {
+            let age = (max_state - state.min(max_state)) as f32;
+            let t =
+                age / (max_state.max(2) - 1) as f32 * (colors.len() - 1) as f32;
+            let (i, f) = (t as usize, t.fract());
+            let next = colors[(i + 1).min(colors.len() - 1)];
+            [0, 1, 2].map(|c| {
+                (colors[i][c] as f32 * (1. - f) + next[c] as f32 * f).round()
+                    as u8
+            })
+        }
+        // Keep in sync with `MAX_STATE` in `draw.wgsl`.
+        _ => [(state as f32 / 5.).min(1.) * 255.; 3].map(|c| c as u8),
+    }
+}
+
+impl FromStr for Palette {
+    type Err = anyhow::Error;
+
+    /// Parse a list of hex colors like `#ffcc00, #202040`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = s
+            .split(',')
+            .map(parse_color)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if colors.len() > MAX_COLORS {
+            bail!("a palette has at most {MAX_COLORS} colors");
+        }
+        Ok(Palette(colors))
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colors: Vec<_> = self.0.iter().copied().map(format_color).collect();
+        write!(f, "{}", colors.join(", "))
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PaletteUniform {
+    colors: [[f32; 4]; MAX_COLORS],
+    /// Number of colors, 0 for the default gray scale.
+    len: u32,
+    max_state: u32,
+    _padding: [u32; 2],
+}
+
+impl PaletteUniform {
+    fn new(palette: Option<&Palette>, max_state: u8) -> Self {
+        let mut uniform = Self {
+            colors: [[0.; 4]; MAX_COLORS],
+            len: 0,
+            max_state: max_state as u32,
+            _padding: [0; 2],
+        };
+        if let Some(Palette(colors)) = palette {
+            for (dst, [r, g, b]) in uniform.colors.iter_mut().zip(colors) {
+                *dst = [*r, *g, *b, u8::MAX].map(|c| c as f32 / 255.);
+            }
+            uniform.len = colors.len() as u32;
+        }
+        uniform
+    }
+}
+
+/// The palette as uniform of the render pipeline.
+pub struct PaletteBinding {
+    pub bind_group: BindGroup,
+    pub bind_group_layout: BindGroupLayout,
+    buffer: Buffer,
+}
+
+impl PaletteBinding {
+    pub fn new(
+        device: &Device,
+        palette: Option<&Palette>,
+        max_state: u8,
+    ) -> Self {
+        let buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Palette Buffer"),
+                contents: bytemuck::bytes_of(&PaletteUniform::new(
+                    palette, max_state,
+                )),
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Palette Bind Group Layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Palette Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            bind_group,
+            bind_group_layout,
+            buffer,
+        }
+    }
+
+    pub fn update(
+        &self,
+        queue: &Queue,
+        palette: Option<&Palette>,
+        max_state: u8,
+    ) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&PaletteUniform::new(palette, max_state)),
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! Patterns in a text format like Golly's RLE, with `/` between the layers
+//! along z as in Golly's 3D.lua:
+//!
+//! ```text
+//! #N glider
+//! x = 3, y = 3, z = 2, rule = 4/4/5/M
+//! bo$2bo$3o/3o!
+//! ```
+//!
+//! 2D patterns, in Golly's RLE or the plaintext format of LifeWiki, are read
+//! as a single layer.
+
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context};
+use ndarray::Array3;
+
+use crate::{
+    game_of_life::{GameOfLife, MAX_SIZE},
+    palette::Palette,
+    rule::Rule,
+    rule_parse,
+    stats::GenerationStats,
+    vox, Extent,
+};
+
+/// File extension of patterns.
+pub const EXTENSION: &str = "rle";
+/// File extension of LifeWiki's plaintext patterns.
+const PLAINTEXT_EXTENSION: &str = "cells";
+/// Longest line written, as in Golly.
+const LINE_WIDTH: usize = 70;
+/// Turns the layer of a 2D pattern onto the board, which lies along x and z.
+const ON_BOARD: Orientation = Orientation {
+    axes: [0, 2, 1],
+    flip: [false, false, true],
+};
+/// Layer along y the board puts patterns on by default.
+const BOARD_HEIGHT: usize = 11;
+/// The pattern of `--board` without `--pattern`.
+const GLIDER: &str = "#N Glider\nx = 3, y = 3\nbo$2bo$3o!\n";
+
+/// A box of cells with an optional rule.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// States as in Golly: 0 is dead, 1 alive and higher states dying.
+    /// Indexed by x, y and z.
+    pub cells: Array3<u8>,
+    pub rule: Option<Rule>,
+    /// The `#` lines before the header, such as `#N name`, without the `#`.
+    pub comments: Vec<String>,
+}
+
+impl Pattern {
+    /// The box of `size` cells starting at `min`, clipped to the grid.
+    pub fn crop(gol: &GameOfLife, min: [usize; 3], size: [usize; 3]) -> Self {
+        let dim = <[usize; 3]>::from(gol.cells.dim());
+        let [x, y, z] = [0, 1, 2]
+            .map(|i| min[i].min(dim[i])..(min[i] + size[i]).min(dim[i]));
+        let region = gol.cells.slice(ndarray::s![x, y, z]);
+        let max_state = gol.rule.max_state;
+        Self {
+            cells: region.map(|&c| if c == 0 { 0 } else { max_state + 1 - c }),
+            rule: Some(gol.rule.clone()),
+            comments: Vec::new(),
+        }
+    }
+
+    /// The bounding box of the live cells, if there are any.
+    pub fn crop_live(gol: &GameOfLife) -> Option<Self> {
+        let [min, max] = GenerationStats::collect(gol, None).bounding_box?;
+        Some(Self::crop(gol, min, [0, 1, 2].map(|i| max[i] - min[i] + 1)))
+    }
+
+    /// The pattern turned by `orientation`.
+    pub fn turned(&self, orientation: Orientation) -> Self {
+        let dim = <[usize; 3]>::from(self.cells.dim());
+        let [x, y, z] = orientation.axes.map(|a| dim[a]);
+        let cells = Array3::from_shape_fn((x, y, z), |(x, y, z)| {
+            let mut p = [0; 3];
+            for (i, q) in [x, y, z].into_iter().enumerate() {
+                let a = orientation.axes[i];
+                p[a] = if orientation.flip[i] {
+                    dim[a] - 1 - q
+                } else {
+                    q
+                };
+            }
+            self.cells[p]
+        });
+        Self {
+            cells,
+            rule: self.rule.clone(),
+            comments: self.comments.clone(),
+        }
+    }
+
+    /// Put the pattern on `cells`, turned by `orientation` and centered on
+    /// `center`, and return the number of live cells that did not fit. The
+    /// dead cells of the pattern replace those below them, and states the
+    /// rule does not have become live cells.
+    pub fn place(
+        &self,
+        cells: &mut Array3<u8>,
+        max_state: u8,
+        center: [usize; 3],
+        orientation: Orientation,
+    ) -> usize {
+        let turned = self.turned(orientation);
+        let dim = <[usize; 3]>::from(turned.cells.dim());
+        let grid = <[usize; 3]>::from(cells.dim());
+        let corner =
+            [0, 1, 2].map(|i| center[i] as isize - dim[i] as isize / 2);
+        let mut dropped = 0;
+        for ((x, y, z), &state) in turned.cells.indexed_iter() {
+            let target = [x, y, z].map(|p| p as isize);
+            let target = [0, 1, 2].map(|i| corner[i] + target[i]);
+            if (0..3).any(|i| target[i] < 0 || target[i] >= grid[i] as isize) {
+                dropped += usize::from(state != 0);
+                continue;
+            }
+            cells[target.map(|t| t as usize)] = match state {
+                0 => 0,
+                s if s > max_state => max_state,
+                s => max_state + 1 - s,
+            };
+        }
+        dropped
+    }
+
+    /// Read an RLE file, or a plaintext or MagicaVoxel file if it ends in
+    /// `.cells` or `.vox`.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        if path.extension().is_some_and(|e| e == vox::EXTENSION) {
+            return vox::read(path);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        if path.extension().is_some_and(|e| e == PLAINTEXT_EXTENSION) {
+            Self::parse_plaintext(&text)
+        } else {
+            text.parse()
+        }
+        .with_context(|| format!("invalid pattern {}", path.display()))
+    }
+
+    /// Parse LifeWiki's plaintext format: `!` comment lines followed by
+    /// rows of `.` for dead and `O` for live cells.
+    pub fn parse_plaintext(text: &str) -> anyhow::Result<Self> {
+        let mut comments = Vec::new();
+        let mut rows = Vec::new();
+        for line in text.lines().map(str::trim_end) {
+            match line.strip_prefix('!') {
+                Some(comment) => comments.push(comment.to_string()),
+                None => rows.push(line),
+            }
+        }
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width > MAX_SIZE || rows.len() > MAX_SIZE {
+            bail!("patterns can be at most {MAX_SIZE} cells along an axis");
+        }
+        let mut cells = Array3::zeros((width, rows.len(), 1));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                cells[[x, y, 0]] = match c {
+                    '.' => 0,
+                    'O' | 'o' | '*' => 1,
+                    c => bail!("unexpected `{c}` in row {}", y + 1),
+                };
+            }
+        }
+        Ok(Self {
+            cells,
+            rule: None,
+            comments,
+        })
+    }
+
+    /// The pattern repeated `layers` times along z.
+    pub fn stacked(&self, layers: usize) -> anyhow::Result<Self> {
+        let depth = self.cells.dim().2;
+        if layers == 0 || depth.saturating_mul(layers) > MAX_SIZE {
+            bail!(
+                "the pattern can be stacked 1 to {} times",
+                MAX_SIZE / depth.max(1)
+            );
+        }
+        let views = vec![self.cells.view(); layers];
+        Ok(Self {
+            cells: ndarray::concatenate(ndarray::Axis(2), &views)?,
+            ..self.clone()
+        })
+    }
+
+    /// Write the pattern to `path`, or stdout if there is none.
+    pub fn write(&self, path: Option<&Path>) -> anyhow::Result<()> {
+        let mut out: Box<dyn Write> = match path {
+            Some(path) => {
+                Box::new(BufWriter::new(File::create(path).with_context(
+                    || format!("could not create {}", path.display()),
+                )?))
+            }
+            None => Box::new(BufWriter::new(io::stdout())),
+        };
+        write!(out, "{self}")?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().map(str::trim);
+        let mut comments = Vec::new();
+        let header = loop {
+            match lines.next() {
+                Some(line) if line.starts_with('#') => {
+                    comments.push(line[1..].to_string())
+                }
+                Some("") => {}
+                Some(line) => break line,
+                None => bail!("expected a header like `x = 3, y = 3, z = 2`"),
+            }
+        };
+        let (dim, rule) = parse_header(header)?;
+        let mut cells = Array3::zeros((dim[0], dim[1], dim[2]));
+        let [mut x, mut y, mut z] = [0usize; 3];
+        let mut count = None::<usize>;
+        let mut prefix = None;
+        for c in lines.flat_map(str::chars) {
+            let n = count.unwrap_or(1);
+            let state = match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit));
+                    if count.is_none() {
+                        bail!("the run count is too large");
+                    }
+                    continue;
+                }
+                'p'..='y' if prefix.is_none() => {
+                    prefix = Some(c as usize - 'p' as usize);
+                    continue;
+                }
+                'b' | '.' => Some(0),
+                'o' => Some(1),
+                'A'..='X' => {
+                    let base = prefix.take().map_or(0, |p| 24 * (p + 1));
+                    let state = base + c as usize - 'A' as usize + 1;
+                    Some(u8::try_from(state).context("states go up to 255")?)
+                }
+                '$' => {
+                    (x, y) = (0, y.checked_add(n).context("too many rows")?);
+                    None
+                }
+                '/' => {
+                    let layer = z.checked_add(n).context("too many layers")?;
+                    (x, y, z) = (0, 0, layer);
+                    None
+                }
+                '!' => break,
+                c if c.is_whitespace() => continue,
+                c => bail!("unexpected `{c}` in the cells"),
+            };
+            if prefix.is_some() {
+                bail!("expected a state letter after the prefix");
+            }
+            count = None;
+            if let Some(state) = state {
+                let end = x.checked_add(n).context("the row is too long")?;
+                if state != 0 {
+                    for x in x..end {
+                        *cells.get_mut([x, y, z]).with_context(|| {
+                            format!("cell {x},{y},{z} lies outside the header")
+                        })? = state;
+                    }
+                }
+                x = end;
+            }
+        }
+        Ok(Self {
+            cells,
+            rule,
+            comments,
+        })
+    }
+}
+
+/// Parse a header like `x = 3, y = 3, z = 2, rule = 4/4/5/M`, where `z` and
+/// the rule are optional. The rule may contain commas, so it comes last.
+/// Without `z` it is the header of a 2D pattern, whose rule is ignored as it
+/// means something else on a plane.
+fn parse_header(line: &str) -> anyhow::Result<([usize; 3], Option<Rule>)> {
+    let (dims, rule) = match line.find("rule") {
+        Some(i) => (&line[..i], Some(&line[i + "rule".len()..])),
+        None => (line, None),
+    };
+    let mut dim = [None; 3];
+    for part in dims.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .with_context(|| format!("expected `key = value`, not `{part}`"))?;
+        let key = key.trim();
+        let axis = match key {
+            "x" => 0,
+            "y" => 1,
+            "z" => 2,
+            key => bail!("unknown header key `{key}`"),
+        };
+        let size: usize = value.trim().parse()?;
+        if size > MAX_SIZE {
+            bail!("patterns can be at most {MAX_SIZE} cells along {key}");
+        }
+        dim[axis] = Some(size);
+    }
+    let [Some(x), Some(y), z] = dim else {
+        bail!("expected a header like `x = 3, y = 3, z = 2`");
+    };
+    let Some(z) = z else {
+        return Ok(([x, y, 1], None));
+    };
+    let rule = rule
+        .map(|rule| {
+            let mut rule = rule.trim().trim_start_matches('=').to_string();
+            rule.retain(|c| !c.is_whitespace());
+            rule_parse::rule_and_init(&rule)
+                .map(|(rule, _)| rule)
+                .with_context(|| format!("could not parse rule {rule}"))
+        })
+        .transpose()?;
+    Ok(([x, y, z], rule))
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for comment in &self.comments {
+            writeln!(f, "#{comment}")?;
+        }
+        let (x, y, z) = self.cells.dim();
+        write!(f, "x = {x}, y = {y}, z = {z}")?;
+        if let Some(rule) = &self.rule {
+            write!(f, ", rule = {rule}")?;
+        }
+        writeln!(f)?;
+
+        let multi_state = match &self.rule {
+            Some(rule) => rule.max_state > 1,
+            None => self.cells.iter().any(|&c| c > 1),
+        };
+        // Runs of states and separators, with the trailing dead cells of
+        // every row and the trailing separators of every layer dropped.
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        for layer in self.cells.axis_iter(ndarray::Axis(2)) {
+            for row in layer.axis_iter(ndarray::Axis(1)) {
+                let len =
+                    row.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+                for &state in row.iter().take(len) {
+                    push_run(&mut runs, state_symbol(state, multi_state));
+                }
+                push_run(&mut runs, "$".to_string());
+            }
+            while runs.last().is_some_and(|(_, s)| s == "$") {
+                runs.pop();
+            }
+            push_run(&mut runs, "/".to_string());
+        }
+        while runs.last().is_some_and(|(_, s)| s == "/") {
+            runs.pop();
+        }
+        runs.push((1, "!".to_string()));
+
+        let mut line = String::new();
+        for (count, symbol) in runs {
+            let run = match count {
+                1 => symbol,
+                n => format!("{n}{symbol}"),
+            };
+            if line.len() + run.len() > LINE_WIDTH {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            line += &run;
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+/// Add `symbol` to the last run if it repeats it, or start a new run.
+fn push_run(runs: &mut Vec<(usize, String)>, symbol: String) {
+    match runs.last_mut() {
+        Some((count, last)) if *last == symbol => *count += 1,
+        _ => runs.push((1, symbol)),
+    }
+}
+
+/// The letter of a Golly state: `b` and `o`, or `.`, `A` to `X`, `pA` to
+/// `pX` and so on for more than two states.
+fn state_symbol(state: u8, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (1..=24, true) => ((b'A' + state - 1) as char).to_string(),
+        (_, true) => {
+            let prefix = (b'p' + (state - 25) / 24) as char;
+            let letter = (b'A' + (state - 25) % 24) as char;
+            format!("{prefix}{letter}")
+        }
+    }
+}
+
+/// How a pattern is turned when placed: the axis of the pattern each axis
+/// of the grid is taken from, and whether it is mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orientation {
+    pub axes: [usize; 3],
+    pub flip: [bool; 3],
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Self {
+            axes: [0, 1, 2],
+            flip: [false; 3],
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = anyhow::Error;
+
+    /// Parse the pattern axes along x, y and z of the grid, each optionally
+    /// mirrored by a `-`: `xyz` keeps the pattern as it is, `zyx` swaps x
+    /// and z and `-yxz` turns it by 90° around z.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut orientation = Self::default();
+        let mut chars = s.chars();
+        for i in 0..3 {
+            let mut c = chars.next();
+            if c == Some('-') {
+                orientation.flip[i] = true;
+                c = chars.next();
+            }
+            orientation.axes[i] = match c {
+                Some('x') => 0,
+                Some('y') => 1,
+                Some('z') => 2,
+                _ => bail!("expected three axes like `xyz` or `-yxz`"),
+            };
+        }
+        let mut axes = orientation.axes;
+        axes.sort();
+        if chars.next().is_some() || axes != [0, 1, 2] {
+            bail!("expected each of x, y and z once, like `xyz` or `-yxz`");
+        }
+        Ok(orientation)
+    }
+}
+
+impl Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (axis, flip) in self.axes.iter().zip(self.flip) {
+            if flip {
+                write!(f, "-")?;
+            }
+            write!(f, "{}", ['x', 'y', 'z'][*axis])?;
+        }
+        Ok(())
+    }
+}
+
+/// A pattern and where to put it on the grid.
+#[derive(Debug, Clone)]
+pub struct Placement {
+    pub pattern: Pattern,
+    /// Center of the pattern [default: the center of the grid].
+    pub center: Option<[Extent; 3]>,
+    pub orientation: Orientation,
+    /// Whether the pattern lies on the slab of [`GameOfLife::gol_2d_board`].
+    pub board: bool,
+}
+
+impl Placement {
+    /// A glider on the 2D board.
+    pub fn glider_board() -> Self {
+        Self {
+            pattern: GLIDER.parse().unwrap(),
+            center: None,
+            orientation: Orientation::default(),
+            board: true,
+        }
+    }
+
+    /// A grid of edge length `size` with the pattern on it, and otherwise
+    /// empty or the 2D board.
+    pub fn cells(&self, size: usize, max_state: u8) -> Array3<u8> {
+        let center = match self.center {
+            Some(center) => center.map(|c| c.resolve(size)),
+            None if self.board => [size / 2, BOARD_HEIGHT, size / 2],
+            None => [size / 2; 3],
+        };
+        let (mut cells, pattern, orientation) = if self.board {
+            (
+                GameOfLife::gol_2d_board(size, center[1], max_state),
+                self.pattern.turned(self.orientation),
+                ON_BOARD,
+            )
+        } else {
+            (
+                Array3::zeros((size, size, size)),
+                self.pattern.clone(),
+                self.orientation,
+            )
+        };
+        let dropped = pattern.place(&mut cells, max_state, center, orientation);
+        if dropped > 0 {
+            log::warn!("{dropped} cells of the pattern lie outside the grid");
+        }
+        cells
+    }
+}
+
+/// Simulate `generations` generations and write the box of `size` cells at
+/// `min`, or the bounding box of the live cells, as pattern, or as
+/// MagicaVoxel file in the colors of `palette` if `output` ends in `.vox`.
+pub fn run(
+    mut gol: GameOfLife,
+    generations: u64,
+    region: Option<(&[usize], &[usize])>,
+    output: Option<&Path>,
+    palette: Option<&Palette>,
+) -> anyhow::Result<()> {
+    while gol.generation < generations {
+        gol.update();
+    }
+    let pattern = match region {
+        Some((&[x, y, z], &[dx, dy, dz])) => {
+            Pattern::crop(&gol, [x, y, z], [dx, dy, dz])
+        }
+        Some(_) => bail!("the region must be given as x,y,z"),
+        None => Pattern::crop_live(&gol).with_context(|| {
+            format!("no live cells in generation {}", gol.generation)
+        })?,
+    };
+    match output {
+        Some(path) if path.extension().is_some_and(|e| e == vox::EXTENSION) => {
+            vox::write(&pattern, palette, path)
+        }
+        _ => pattern.write(output),
+    }
+}
@@ -1,17 +1,28 @@
-use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+use std::{
+    collections::HashMap, fmt::Display, ops::RangeInclusive, path::PathBuf,
+    str::FromStr,
+};
 
 use ndarray::Array3;
 use ndarray_rand::{
     rand::{self, Rng},
     rand_distr::{Distribution, Standard},
 };
-use nom::Finish;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
     Buffer, BufferUsages, Device,
 };
 
-use crate::rule_parse;
+use crate::{
+    args::Args,
+    rule_file,
+    rule_parse::{self, Field, ParseRuleError},
+};
+
+/// Highest number of states reachable through [`Rule::change_states`].
+pub const MAX_STATES: u8 = 20;
+/// Number of random rules tried by [`Rule::random_with_lambda`].
+pub const MAX_LAMBDA_ATTEMPTS: usize = 10_000;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Neighborhood {
@@ -32,6 +43,53 @@ impl Display for Neighborhood {
     }
 }
 
+impl Neighborhood {
+    /// Number of neighbors of a cell, which is the highest neighbor count a
+    /// rule can ever see.
+    pub fn size(&self) -> u8 {
+        match self {
+            Neighborhood::Moore | Neighborhood::MooreNonWrapping => 26,
+            Neighborhood::VonNeumann | Neighborhood::VonNeumannNonWrapping => 6,
+        }
+    }
+
+    /// Whether the grid wraps around at its faces.
+    pub fn is_wrapping(&self) -> bool {
+        matches!(self, Neighborhood::Moore | Neighborhood::VonNeumann)
+    }
+
+    /// The same kernel, wrapping around at the faces of the grid or not.
+    pub fn with_wrapping(&self, wrapping: bool) -> Self {
+        match (self, wrapping) {
+            (Neighborhood::Moore | Neighborhood::MooreNonWrapping, true) => {
+                Neighborhood::Moore
+            }
+            (Neighborhood::Moore | Neighborhood::MooreNonWrapping, false) => {
+                Neighborhood::MooreNonWrapping
+            }
+            (
+                Neighborhood::VonNeumann | Neighborhood::VonNeumannNonWrapping,
+                true,
+            ) => Neighborhood::VonNeumann,
+            (
+                Neighborhood::VonNeumann | Neighborhood::VonNeumannNonWrapping,
+                false,
+            ) => Neighborhood::VonNeumannNonWrapping,
+        }
+    }
+
+    /// The following neighborhood, in the order of their codes in the rule
+    /// format.
+    pub fn next(&self) -> Self {
+        match self {
+            Neighborhood::Moore => Neighborhood::MooreNonWrapping,
+            Neighborhood::MooreNonWrapping => Neighborhood::VonNeumann,
+            Neighborhood::VonNeumann => Neighborhood::VonNeumannNonWrapping,
+            Neighborhood::VonNeumannNonWrapping => Neighborhood::Moore,
+        }
+    }
+}
+
 impl Distribution<Neighborhood> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Neighborhood {
         match rng.gen_range(0..=3) {
@@ -57,23 +115,166 @@ impl Rule {
         born: U,
         max_state: u8,
         neighborhood: Neighborhood,
-    ) -> Self {
-        Self {
-            survive_mask: survive.to_bit_mask(),
-            born_mask: born.to_bit_mask(),
+    ) -> Result<Self, ParseRuleError> {
+        Ok(Self {
+            survive_mask: survive.to_bit_mask()?,
+            born_mask: born.to_bit_mask().map_err(|e| ParseRuleError {
+                field: Field::BornMask,
+                ..e
+            })?,
             max_state,
             neighborhood,
-        }
+        })
     }
     pub fn new_random() -> Self {
+        Self::random_using(&mut rand::thread_rng())
+    }
+    /// A random rule that only sets bits the neighborhood can reach, and
+    /// never bit 0.
+    pub fn random_using<R: Rng>(rng: &mut R) -> Self {
+        let neighborhood: Neighborhood =
+            rng.sample(rand::distributions::Standard);
+        let mask = relevant_mask(neighborhood) & !1;
         Self {
-            survive_mask: rand::random::<u32>() & (u32::MAX - 1),
-            born_mask: rand::random::<u32>() & (u32::MAX - 1),
-            max_state: rand::random::<u8>() / 64 + 1,
-            neighborhood: rand::thread_rng()
-                .sample(rand::distributions::Standard),
+            survive_mask: rng.gen::<u32>() & mask,
+            born_mask: rng.gen::<u32>() & mask,
+            max_state: rng.gen::<u8>() / 64 + 1,
+            neighborhood,
+        }
+    }
+    /// A random rule whose [`Rule::lambda`] lies within `band`, or `None` if
+    /// none was found after [`MAX_LAMBDA_ATTEMPTS`] tries.
+    ///
+    /// Each try sets bits with a different probability, so that both sparse
+    /// and dense rules are sampled.
+    pub fn random_with_lambda<R: Rng>(
+        rng: &mut R,
+        band: &RangeInclusive<f64>,
+    ) -> Option<Self> {
+        (0..MAX_LAMBDA_ATTEMPTS).find_map(|_| {
+            let mut rule = Self::random_using(rng);
+            let density: f64 = rng.gen();
+            let size = rule.neighborhood.size();
+            let mut bits = || {
+                (1..=size)
+                    .filter(|_| rng.gen_bool(density))
+                    .fold(0, |acc, i| acc | 1 << i)
+            };
+            rule.survive_mask = bits();
+            rule.born_mask = bits();
+            band.contains(&rule.lambda()).then_some(rule)
+        })
+    }
+    /// Flip one random survive or born bit the neighborhood can reach,
+    /// except bit 0.
+    pub fn flip_random_bit<R: Rng>(&mut self, rng: &mut R) {
+        let bit = 1 << rng.gen_range(1..=self.neighborhood.size());
+        if rng.gen() {
+            self.survive_mask ^= bit;
+        } else {
+            self.born_mask ^= bit;
         }
     }
+    /// Add `delta` to the number of states without going past 2 or
+    /// [`MAX_STATES`]. A rule already outside those bounds is never moved
+    /// further out, but not pulled back in either.
+    pub fn change_states(&mut self, delta: i8) {
+        let changed = self.max_state.saturating_add_signed(delta);
+        self.max_state = if delta > 0 {
+            changed.min(self.max_state.max(MAX_STATES - 1))
+        } else {
+            changed.max(self.max_state.min(1))
+        };
+    }
+    /// Langton's lambda: the fraction of all transitions, over every cell
+    /// state and every configuration of the neighbors' states, that lead to
+    /// a non-zero state.
+    ///
+    /// A neighbor only counts if it is in the highest state, so the neighbor
+    /// count of a random configuration is binomially distributed.
+    pub fn lambda(&self) -> f64 {
+        let states = self.max_state as f64 + 1.;
+        let size = self.neighborhood.size();
+        let p = 1. / states;
+        let mut binomial = 1.;
+        let mut live = 0.;
+        for n in 0..=size {
+            if n > 0 {
+                binomial *= (size - n + 1) as f64 / n as f64;
+            }
+            let probability =
+                binomial * p.powi(n as i32) * (1. - p).powi((size - n) as i32);
+            live += probability
+                * (self.born(n) as u8 + self.survive(n) as u8) as f64;
+        }
+        // Every other state decays into a live state.
+        (live + states - 2.) / states
+    }
+    /// Number of survive bits the neighborhood can reach.
+    pub fn survive_count(&self) -> u32 {
+        (self.survive_mask & relevant_mask(self.neighborhood)).count_ones()
+    }
+    /// Number of born bits the neighborhood can reach.
+    pub fn born_count(&self) -> u32 {
+        (self.born_mask & relevant_mask(self.neighborhood)).count_ones()
+    }
+    /// Whether dead cells without live neighbors stay dead, i.e. born bit 0
+    /// is not set.
+    pub fn is_quiescent(&self) -> bool {
+        !self.born(0)
+    }
+    /// The canonical form of the rule, in which bits the neighborhood can
+    /// never reach are cleared. With a single state nothing is ever alive, so
+    /// such a rule has no bits at all. Rules that behave the same have the
+    /// same normalized form, whatever notation they were written in.
+    pub fn normalized(&self) -> Self {
+        let mask = if self.max_state == 0 {
+            0
+        } else {
+            relevant_mask(self.neighborhood)
+        };
+        Self {
+            survive_mask: self.survive_mask & mask,
+            born_mask: self.born_mask & mask,
+            ..self.clone()
+        }
+    }
+    /// A short identifier of the normalized rule.
+    ///
+    /// It is derived with FNV-1a rather than `DefaultHasher`, whose output
+    /// may change between Rust versions, so that it stays the same
+    /// everywhere.
+    pub fn id(&self) -> String {
+        let rule = RuleRaw::from(&self.normalized());
+        let hash = [
+            rule.survive_mask,
+            rule.born_mask,
+            rule.max_state,
+            rule.neighborhood,
+        ]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:08x}", (hash >> 32) ^ (hash & 0xffffffff))
+    }
+    /// A one-line summary of [`Rule::lambda`] and the other rule metrics.
+    pub fn analysis(&self) -> String {
+        format!(
+            "id {}, lambda {:.3}, {} survive and {} born bits of {}, {}",
+            self.id(),
+            self.lambda(),
+            self.survive_count(),
+            self.born_count(),
+            self.neighborhood.size() + 1,
+            if self.is_quiescent() {
+                "quiescent"
+            } else {
+                "not quiescent"
+            }
+        )
+    }
     pub fn as_buffer(&self, device: &Device) -> Buffer {
         device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Rule Buffer"),
@@ -268,66 +469,143 @@ impl Display for Rule {
     }
 }
 
+/// The ways a rule can be written, all of which can be parsed back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Notation {
+    /// `survive/born/states/neighborhood`, e.g. `4/4/5/M`
+    Native,
+    /// Like native, as used by Visions of Chaos, with `VN` for von Neumann
+    Softology,
+    /// Born first and prefixed, e.g. `B4/S4/5/M`
+    Bs,
+    /// Survive first and prefixed, e.g. `S4/B4/5M`
+    Sb,
+    /// Golly's Generations form, e.g. `B4/S4/C5`, with the neighborhood
+    /// appended unless it is Moore
+    Generations,
+}
+
+impl Rule {
+    /// The rule written in `notation`.
+    pub fn to_notation(&self, notation: Notation) -> String {
+        let survive = bit_run_string(self.survive_mask as u64);
+        let born = bit_run_string(self.born_mask as u64);
+        let states = self.max_state + 1;
+        let neighborhood = self.neighborhood;
+        match notation {
+            Notation::Native => self.to_string(),
+            Notation::Softology => {
+                let neighborhood = match neighborhood {
+                    Neighborhood::VonNeumann => "VN".to_string(),
+                    n => n.to_string(),
+                };
+                format!("{survive}/{born}/{states}/{neighborhood}")
+            }
+            Notation::Bs => {
+                format!("B{born}/S{survive}/{states}/{neighborhood}")
+            }
+            Notation::Sb => {
+                format!("S{survive}/B{born}/{states}{neighborhood}")
+            }
+            Notation::Generations => match neighborhood {
+                Neighborhood::Moore => format!("B{born}/S{survive}/C{states}"),
+                n => format!("B{born}/S{survive}/C{states}/{n}"),
+            },
+        }
+    }
+}
+
 impl FromStr for Rule {
-    type Err = nom::error::Error<String>;
+    type Err = ParseRuleError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match rule_parse::rule_and_init(s).finish() {
-            Ok((_, (r, _))) => Ok(r),
-            Err(nom::error::Error { input, code }) => Err(nom::error::Error {
-                input: input.to_string(),
-                code,
-            }),
+        rule_parse::rule_and_init(s).map(|(rule, _)| rule)
+    }
+}
+
+/// Print the ID and normalized form of every rule file in `files`, or of the
+/// rule given by `args` if there are none, and point out duplicates.
+pub fn normalize(
+    args: &Args,
+    files: &[PathBuf],
+    notation: Notation,
+) -> anyhow::Result<()> {
+    let rules = if files.is_empty() {
+        vec![(args.rule_and_init()?.0, None)]
+    } else {
+        let mut rules = Vec::new();
+        for path in files {
+            for (label, entry) in rule_file::read_labeled(path)? {
+                rules.push((entry.rule, Some(label)));
+            }
+        }
+        rules
+    };
+
+    let mut seen: HashMap<String, Option<String>> = HashMap::new();
+    for (rule, label) in rules {
+        let id = rule.id();
+        let mut line =
+            format!("{id} {}", rule.normalized().to_notation(notation));
+        if let Some(label) = &label {
+            line += &format!(" {label}");
         }
+        match seen.get(&id) {
+            Some(Some(first)) => line += &format!(" (same as {first})"),
+            _ => {
+                seen.insert(id, label);
+            }
+        }
+        println!("{line}");
     }
+    Ok(())
+}
+
+/// The bits for neighbor counts 0 up to the size of `neighborhood`.
+fn relevant_mask(neighborhood: Neighborhood) -> u32 {
+    (1 << (neighborhood.size() + 1)) - 1
 }
 
+/// Something that describes the neighbor counts of a survive or born mask.
+/// Counts of 32 and more do not fit into the mask and are rejected.
 pub trait ToBitMask {
-    fn to_bit_mask(self) -> u32;
+    fn to_bit_mask(self) -> Result<u32, ParseRuleError>;
 }
 impl ToBitMask for RangeInclusive<u8> {
-    fn to_bit_mask(self) -> u32 {
+    fn to_bit_mask(self) -> Result<u32, ParseRuleError> {
         let mut mask = 0;
         for i in self {
-            mask |= 1 << i;
+            mask |= i.to_bit_mask()?;
         }
-        mask
+        Ok(mask)
     }
 }
 impl<F> ToBitMask for F
 where
     F: Fn(u8) -> bool,
 {
-    fn to_bit_mask(self) -> u32 {
+    fn to_bit_mask(self) -> Result<u32, ParseRuleError> {
         let mut mask = 0;
         for i in 0..=27 {
             if self(i) {
                 mask |= 1 << i;
             }
         }
-        mask
+        Ok(mask)
     }
 }
 impl ToBitMask for u8 {
-    fn to_bit_mask(self) -> u32 {
-        1 << self
+    fn to_bit_mask(self) -> Result<u32, ParseRuleError> {
+        1u32.checked_shl(self as u32).ok_or(ParseRuleError {
+            field: Field::SurviveMask,
+            column: 1,
+            hint: format!("neighbor count {self} does not fit into the mask"),
+        })
     }
 }
 impl ToBitMask for &str {
-    fn to_bit_mask(self) -> u32 {
-        let mut mask = 0;
-        for p in self.split(',') {
-            if p.contains('-') {
-                let mut split = p.split('-');
-                let range = (split.next().unwrap().parse::<u8>().unwrap()
-                    ..=split.next().unwrap().parse::<u8>().unwrap())
-                    .to_bit_mask();
-                mask |= range;
-            } else if !p.is_empty() {
-                mask |= 1 << p.parse::<u8>().unwrap();
-            }
-        }
-        mask
+    fn to_bit_mask(self) -> Result<u32, ParseRuleError> {
+        rule_parse::mask(self)
     }
 }
 
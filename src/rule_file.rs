@@ -0,0 +1,226 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    camera::CameraPose,
+    palette::Palette,
+    rule::{Neighborhood, Rule},
+    rule_parse, Init,
+};
+
+/// One named rule of a `.gol3d` file, with everything needed to show it off.
+#[derive(Debug, Clone)]
+pub struct RuleEntry {
+    pub name: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub rule: Rule,
+    pub init: Init,
+    pub camera: Option<CameraPose>,
+    pub palette: Option<Palette>,
+}
+
+impl RuleEntry {
+    pub fn new(name: &str, rule: Rule, init: Init) -> Self {
+        Self {
+            name: name.to_string(),
+            author: None,
+            description: None,
+            tags: Vec::new(),
+            rule,
+            init,
+            camera: None,
+            palette: None,
+        }
+    }
+
+    /// Set the value of `key`, parsed from `value`.
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        let text = || Some(value.to_string());
+        match key {
+            "author" => self.author = text(),
+            "description" => self.description = text(),
+            "tags" => {
+                self.tags = value
+                    .split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            }
+            "rule" => {
+                let mut rule = value.to_string();
+                rule.retain(|c| !c.is_whitespace());
+                let (rule, init) = rule_parse::rule_and_own_init(&rule)?;
+                self.rule = rule;
+                // An earlier `init` or `seed` stays unless the rule has its
+                // own.
+                if let Some(init) = init {
+                    let seed = self.init.seed;
+                    self.init = init;
+                    self.init.seed = self.init.seed.or(seed);
+                }
+            }
+            "init" => {
+                let mut init = value.to_string();
+                init.retain(|c| !c.is_whitespace());
+                let seed = self.init.seed;
+                self.init = rule_parse::init_spec(&init)?;
+                self.init.seed = self.init.seed.or(seed);
+            }
+            "seed" => self.init.seed = Some(value.parse()?),
+            "camera" => self.camera = Some(value.parse()?),
+            "palette" => self.palette = Some(value.parse()?),
+            _ => bail!("unknown key `{key}`"),
+        }
+        Ok(())
+    }
+}
+
+impl Display for RuleEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "[{}]", self.name)?;
+        if let Some(author) = &self.author {
+            writeln!(f, "author = {author}")?;
+        }
+        if let Some(description) = &self.description {
+            writeln!(f, "description = {description}")?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f, "tags = {}", self.tags.join(", "))?;
+        }
+        writeln!(f, "rule = {}", self.rule)?;
+        writeln!(f, "init = {}", self.init)?;
+        if let Some(camera) = &self.camera {
+            writeln!(f, "camera = {camera}")?;
+        }
+        if let Some(palette) = &self.palette {
+            writeln!(f, "palette = {palette}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse the entries of a rule file, see the README for the format. A file
+/// of a single rule string, as written by older versions, is one entry named
+/// `default_name`.
+pub fn parse(text: &str, default_name: &str) -> anyhow::Result<Vec<RuleEntry>> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| (i + 1, line))
+        .collect();
+    if lines
+        .iter()
+        .all(|(_, line)| !line.starts_with('[') && !line.contains('='))
+    {
+        let mut rule: String = lines.iter().map(|(_, line)| *line).collect();
+        rule.retain(|c| !c.is_whitespace());
+        let (rule, init) = rule_parse::rule_and_init(&rule)?;
+        return Ok(vec![RuleEntry::new(default_name, rule, init)]);
+    }
+
+    // Entries without a `rule` are caught below.
+    let placeholder = Rule {
+        survive_mask: 0,
+        born_mask: 0,
+        max_state: 1,
+        neighborhood: Neighborhood::Moore,
+    };
+    let mut entries: Vec<(usize, bool, RuleEntry)> = Vec::new();
+    let mut names = HashSet::new();
+    for (number, line) in lines {
+        let result = if let Some(name) = line.strip_prefix('[') {
+            match name.strip_suffix(']').map(str::trim) {
+                Some(name) if !name.is_empty() && names.insert(name) => {
+                    let entry = RuleEntry::new(
+                        name,
+                        placeholder.clone(),
+                        Init::default(),
+                    );
+                    entries.push((number, false, entry));
+                    Ok(())
+                }
+                Some(name) if !name.is_empty() => {
+                    Err(anyhow::anyhow!("there already is a rule named {name}"))
+                }
+                _ => Err(anyhow::anyhow!("expected a name like `[shells]`")),
+            }
+        } else if let Some((key, value)) = line.split_once('=') {
+            if entries.is_empty() {
+                names.insert(default_name);
+                let entry = RuleEntry::new(
+                    default_name,
+                    placeholder.clone(),
+                    Init::default(),
+                );
+                entries.push((number, false, entry));
+            }
+            let (_, has_rule, entry) = entries.last_mut().unwrap();
+            let key = key.trim();
+            if key == "rule" && *has_rule {
+                Err(anyhow::anyhow!("the rule is given twice"))
+            } else {
+                *has_rule |= key == "rule";
+                entry.set(key, value.trim())
+            }
+        } else {
+            Err(anyhow::anyhow!("expected `key = value` or `[name]`"))
+        };
+        result.with_context(|| format!("line {number}"))?;
+    }
+    entries
+        .into_iter()
+        .map(|(number, has_rule, entry)| {
+            if !has_rule {
+                bail!("line {number}: {} has no rule", entry.name);
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Read the entries of the rule file at `path`.
+pub fn read(path: &Path) -> anyhow::Result<Vec<RuleEntry>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    parse(&text, &name).with_context(|| path.display().to_string())
+}
+
+/// The entries of the rule file at `path`, with the path as their label, or
+/// `path#name` if the file holds several rules.
+pub fn read_labeled(path: &Path) -> anyhow::Result<Vec<(String, RuleEntry)>> {
+    let entries = read(path)?;
+    let several = entries.len() > 1;
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let label = if several {
+                format!("{}#{}", path.display(), entry.name)
+            } else {
+                path.display().to_string()
+            };
+            (label, entry)
+        })
+        .collect())
+}
+
+/// The `.gol3d` files in `dir`, sorted.
+pub fn files_in(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("could not read {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|f| f.extension().is_some_and(|e| e == "gol3d"));
+    files.sort();
+    Ok(files)
+}
@@ -1,19 +1,135 @@
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while1},
+    bytes::complete::{tag, tag_no_case, take_while1},
     character,
-    combinator::{map, map_res, opt, value},
+    character::complete::{digit0, digit1},
+    combinator::{all_consuming, map, map_res, opt, recognize, value, verify},
     multi::separated_list0,
     number,
-    sequence::{preceded, separated_pair, tuple},
-    IResult,
+    sequence::{pair, preceded, separated_pair, terminated, tuple},
+    Finish, IResult,
 };
 
+use std::fmt::Display;
+
 use crate::{
     rule::{Neighborhood, Rule},
-    Init,
+    Extent, Init,
 };
 
+/// The part of a rule string that failed to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    SurviveMask,
+    BornMask,
+    States,
+    Neighborhood,
+    InitSize,
+    InitDensity,
+    Seed,
+    /// A position on the grid on its own, like `--at`.
+    Position,
+    /// Anything after the last field.
+    Trailing,
+}
+
+impl Field {
+    /// The fields of the positional notation, in order.
+    const ALL: [Field; 7] = [
+        Field::SurviveMask,
+        Field::BornMask,
+        Field::States,
+        Field::Neighborhood,
+        Field::InitSize,
+        Field::InitDensity,
+        Field::Seed,
+    ];
+    /// Number of fields every positional rule has.
+    const REQUIRED: usize = 3;
+
+    fn hint(&self) -> &'static str {
+        match self {
+            Field::SurviveMask | Field::BornMask => {
+                "expected a list of neighbor counts like `1,3,5-7` or a \
+                 bitmask like `0b1010`, with counts below 32"
+            }
+            Field::States => "expected the number of states, at least 1",
+            Field::Neighborhood => {
+                "expected one of `M`, `MN`, `N`, `NN`, `VN`, `Moore` or \
+                 `VonNeumann`"
+            }
+            Field::InitSize => {
+                "expected the edge length of the initial cube in cells, as \
+                 percent like `50%` or as fraction like `0.5`, optionally \
+                 followed by its center like `@10,50%,0.5`"
+            }
+            Field::InitDensity => {
+                "expected the density of the initial cube as a number \
+                 between 0 and 1"
+            }
+            Field::Seed => "expected the seed as an integer",
+            Field::Position => {
+                "expected a position like `10,50%,0.5`, in cells or relative \
+                 to the grid"
+            }
+            Field::Trailing => "unexpected text after the last field",
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Field::SurviveMask => "survive mask",
+            Field::BornMask => "born mask",
+            Field::States => "number of states",
+            Field::Neighborhood => "neighborhood",
+            Field::InitSize => "initial size",
+            Field::InitDensity => "initial density",
+            Field::Seed => "seed",
+            Field::Position => "position",
+            Field::Trailing => "rule",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Why and where a rule string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRuleError {
+    pub field: Field,
+    /// 1-based column of the first character that could not be parsed.
+    pub column: usize,
+    pub hint: String,
+}
+
+impl ParseRuleError {
+    fn new(field: Field, column: usize) -> Self {
+        Self {
+            field,
+            column,
+            hint: field.hint().to_string(),
+        }
+    }
+}
+
+impl Display for ParseRuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid {} at column {}: {}",
+            self.field, self.column, self.hint
+        )
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+/// A neighbor count, which has to fit into a `u32` mask.
+fn bit(input: &str) -> IResult<&str, u8> {
+    verify(character::complete::u8, |n| *n < 32)(input)
+}
+
 fn bitmask(input: &str) -> IResult<&str, u32> {
     alt((
         preceded(
@@ -28,17 +144,15 @@ fn bitmask(input: &str) -> IResult<&str, u32> {
                 alt((
                     // Range of bits
                     map(
-                        separated_pair(
-                            character::complete::u8,
-                            tag("-"),
-                            character::complete::u8,
-                        ),
+                        verify(separated_pair(bit, tag("-"), bit), |(l, r)| {
+                            l <= r
+                        }),
                         |(l, r)| {
                             ((1 << l) - 1) ^ (((1u64 << (r + 1)) - 1) as u32)
                         },
                     ),
                     // Single bit
-                    map(character::complete::u8, |n| 1 << n),
+                    map(bit, |n| 1 << n),
                 )),
             ),
             |l| l.into_iter().fold(0, |acc, m| acc | m),
@@ -46,47 +160,305 @@ fn bitmask(input: &str) -> IResult<&str, u32> {
     ))(input)
 }
 
-pub fn rule_and_init(input: &str) -> IResult<&str, (Rule, Init)> {
+fn neighborhood(input: &str) -> IResult<&str, Neighborhood> {
+    alt((
+        value(Neighborhood::MooreNonWrapping, tag("MN")),
+        value(Neighborhood::VonNeumannNonWrapping, tag("NN")),
+        value(Neighborhood::VonNeumann, tag("VN")),
+        value(Neighborhood::Moore, tag("M")),
+        value(Neighborhood::VonNeumann, tag("N")),
+    ))(input)
+}
+
+/// A neighborhood code or its name, e.g. `Moore` or `vonNeumann`.
+fn neighborhood_name(input: &str) -> IResult<&str, Neighborhood> {
+    alt((
+        value(Neighborhood::Moore, tag_no_case("moore")),
+        value(Neighborhood::VonNeumann, tag_no_case("vonneumann")),
+        value(Neighborhood::VonNeumann, tag_no_case("von-neumann")),
+        neighborhood,
+    ))(input)
+}
+
+/// Parse all of `input`, which starts at `column`, with `parser`.
+fn field<'a, T>(
+    field: Field,
+    input: &'a str,
+    column: usize,
+    parser: impl FnMut(&'a str) -> IResult<&'a str, T>,
+) -> Result<T, ParseRuleError> {
+    all_consuming(parser)(input)
+        .finish()
+        .map(|(_, v)| v)
+        .map_err(|e| {
+            ParseRuleError::new(field, column + input.len() - e.input.len())
+        })
+}
+
+fn extent_parser(input: &str) -> IResult<&str, Extent> {
+    alt((
+        map(
+            terminated(number::complete::double, tag("%")),
+            Extent::Percent,
+        ),
+        map_res(recognize(tuple((digit1, tag("."), digit0))), |s: &str| {
+            s.parse().map(Extent::Fraction)
+        }),
+        map(character::complete::u64, |n| Extent::Cells(n as usize)),
+    ))(input)
+}
+
+fn density_parser(input: &str) -> IResult<&str, f64> {
+    verify(number::complete::double, |d| (0. ..=1.).contains(d))(input)
+}
+
+/// The size of the initial cube and optionally its center, e.g. `50%` or
+/// `10@25%,50%,50%`.
+fn cube(input: &str) -> IResult<&str, (Extent, Option<[Extent; 3]>)> {
+    pair(extent_parser, opt(preceded(tag("@"), position_parser)))(input)
+}
+
+/// A point on the grid as `x,y,z`.
+fn position_parser(input: &str) -> IResult<&str, [Extent; 3]> {
     map(
         tuple((
-            bitmask,
-            preceded(tag("/"), bitmask),
-            preceded(tag("/"), map(character::complete::u8, |n| n - 1)),
-            preceded(
-                tag("/"),
-                alt((
-                    value(Neighborhood::MooreNonWrapping, tag("MN")),
-                    value(Neighborhood::VonNeumannNonWrapping, tag("NN")),
-                    value(Neighborhood::Moore, tag("M")),
-                    value(Neighborhood::VonNeumann, tag("N")),
-                )),
-            ),
-            opt(preceded(
-                tag("/"),
-                map(character::complete::u64, |n| n as usize),
-            )),
-            opt(preceded(tag("/"), number::complete::double)),
+            extent_parser,
+            preceded(tag(","), extent_parser),
+            preceded(tag(","), extent_parser),
         )),
-        |(
+        |(x, y, z)| [x, y, z],
+    )(input)
+}
+
+/// Parse a position on its own, e.g. `10,50%,0.5`.
+pub fn position(input: &str) -> Result<[Extent; 3], ParseRuleError> {
+    self::field(Field::Position, input, 1, position_parser)
+}
+
+/// Parse a length along the grid on its own, e.g. `10`, `50%` or `0.5`.
+pub fn extent(input: &str) -> Result<Extent, ParseRuleError> {
+    self::field(Field::InitSize, input, 1, extent_parser)
+}
+
+/// Parse the density of the initial cube on its own, e.g. `0.4`.
+pub fn density(input: &str) -> Result<f64, ParseRuleError> {
+    self::field(Field::InitDensity, input, 1, density_parser)
+}
+
+/// Parse a survive or born mask on its own, e.g. `1,3,5-7` or `0b1010`.
+pub fn mask(input: &str) -> Result<u32, ParseRuleError> {
+    self::field(Field::SurviveMask, input, 1, bitmask)
+}
+
+fn states(input: &str) -> IResult<&str, u8> {
+    map(verify(character::complete::u8, |n| *n > 0), |n| n - 1)(input)
+}
+
+/// The `/`-separated parts of `input` and the columns they start at.
+fn parts(input: &str) -> Vec<(&str, usize)> {
+    let mut parts = Vec::new();
+    let mut column = 1;
+    for part in input.split('/') {
+        parts.push((part, column));
+        column += part.len() + 1;
+    }
+    parts
+}
+
+/// Parse a rule string in any of the supported notations, see [`Notation`].
+/// The positional ones may be followed by `/size[/density[/seed]]` to set
+/// the initial cube.
+pub fn rule_and_init(input: &str) -> Result<(Rule, Init), ParseRuleError> {
+    let (rule, init) = rule_and_own_init(input)?;
+    Ok((rule, init.unwrap_or_default()))
+}
+
+/// Like [`rule_and_init`], but without an initial cube unless `input` has
+/// one.
+pub fn rule_and_own_init(
+    input: &str,
+) -> Result<(Rule, Option<Init>), ParseRuleError> {
+    let parts = parts(input);
+    let prefixed = input.chars().next().is_some_and(|c| "BbSs".contains(c));
+    if prefixed {
+        prefixed_rule_and_init(&parts)
+    } else {
+        positional_rule_and_init(input, &parts)
+    }
+}
+
+/// `survive/born/states[/neighborhood]`, where a missing neighborhood means
+/// Moore as in the Generations notation.
+fn positional_rule_and_init(
+    input: &str,
+    parts: &[(&str, usize)],
+) -> Result<(Rule, Option<Init>), ParseRuleError> {
+    if parts.len() < Field::REQUIRED {
+        return Err(ParseRuleError::new(
+            Field::ALL[parts.len()],
+            input.len() + 1,
+        ));
+    }
+    let (survive, column) = parts[0];
+    let survive_mask = field(Field::SurviveMask, survive, column, bitmask)?;
+    let (born, column) = parts[1];
+    let born_mask = field(Field::BornMask, born, column, bitmask)?;
+    let (num_states, column) = parts[2];
+    let max_state = field(Field::States, num_states, column, states)?;
+    let neighborhood = match parts.get(3) {
+        Some((code, column)) => {
+            field(Field::Neighborhood, code, *column, neighborhood_name)?
+        }
+        None => Neighborhood::Moore,
+    };
+    Ok((
+        Rule {
             survive_mask,
             born_mask,
             max_state,
             neighborhood,
-            init_size,
-            init_density,
-        )| {
-            (
-                Rule {
-                    survive_mask,
-                    born_mask,
-                    max_state,
-                    neighborhood,
-                },
-                Init {
-                    size: init_size.unwrap_or(10),
-                    density: init_density.unwrap_or(0.5),
-                },
-            )
         },
-    )(input)
+        own_init(parts.get(4..).unwrap_or_default())?,
+    ))
+}
+
+/// `B4/S4/5/M`, `S4/B4/5M`, `B4/S4/C5` and the like: the masks are tagged
+/// with `B` and `S`, the number of states may be tagged with `C` or `G` and
+/// may be followed by the neighborhood directly. Apart from the initial
+/// cube, the parts can come in any order, but an untagged number after the
+/// neighborhood starts the initial cube as in the positional notation.
+fn prefixed_rule_and_init(
+    parts: &[(&str, usize)],
+) -> Result<(Rule, Option<Init>), ParseRuleError> {
+    let mut survive_mask = None;
+    let mut born_mask = None;
+    let mut max_state = None;
+    let mut neighborhood = None;
+    let mut rest = parts;
+    while let Some(((part, column), tail)) = rest.split_first() {
+        let (part, column) = (*part, *column);
+        let first = part.chars().next().unwrap_or_default();
+        let (field_name, tagged) = match first.to_ascii_uppercase() {
+            'S' => (Field::SurviveMask, true),
+            'B' => (Field::BornMask, true),
+            'C' | 'G' => (Field::States, true),
+            c if c.is_ascii_digit()
+                && max_state.is_none()
+                && neighborhood.is_none() =>
+            {
+                (Field::States, false)
+            }
+            c if c.is_ascii_digit() => break,
+            _ => (Field::Neighborhood, false),
+        };
+        let value = if tagged { &part[1..] } else { part };
+        let value_column = column + usize::from(tagged);
+        let duplicate = match field_name {
+            Field::SurviveMask => survive_mask
+                .replace(field(field_name, value, value_column, bitmask)?)
+                .is_some(),
+            Field::BornMask => born_mask
+                .replace(field(field_name, value, value_column, bitmask)?)
+                .is_some(),
+            Field::States => {
+                let (states, code) = field(
+                    field_name,
+                    value,
+                    value_column,
+                    pair(states, opt(neighborhood_name)),
+                )?;
+                max_state = Some(states);
+                code.is_some_and(|code| neighborhood.replace(code).is_some())
+            }
+            _ => neighborhood
+                .replace(field(
+                    field_name,
+                    value,
+                    value_column,
+                    neighborhood_name,
+                )?)
+                .is_some(),
+        };
+        if duplicate {
+            return Err(ParseRuleError {
+                hint: format!("the {field_name} is given twice"),
+                ..ParseRuleError::new(field_name, column)
+            });
+        }
+        rest = tail;
+    }
+    // Both masks have to be tagged, even if empty as in `B/S23`.
+    for (field_name, mask) in [
+        (Field::BornMask, born_mask),
+        (Field::SurviveMask, survive_mask),
+    ] {
+        if mask.is_none() {
+            let end = parts.last().map_or(1, |(p, c)| c + p.len());
+            return Err(ParseRuleError {
+                hint: format!("the {field_name} is missing"),
+                ..ParseRuleError::new(field_name, end)
+            });
+        }
+    }
+    Ok((
+        Rule {
+            survive_mask: survive_mask.unwrap_or(0),
+            born_mask: born_mask.unwrap_or(0),
+            max_state: max_state.unwrap_or(1),
+            neighborhood: neighborhood.unwrap_or(Neighborhood::Moore),
+        },
+        own_init(rest)?,
+    ))
+}
+
+/// Parse the initial cube on its own, e.g. `20/0.4`.
+pub fn init_spec(input: &str) -> Result<Init, ParseRuleError> {
+    init(&parts(input))
+}
+
+/// The initial cube, if there are any parts left for it.
+fn own_init(parts: &[(&str, usize)]) -> Result<Option<Init>, ParseRuleError> {
+    (!parts.is_empty()).then(|| init(parts)).transpose()
+}
+
+/// The optional `size/density/seed` of the initial cube.
+fn init(parts: &[(&str, usize)]) -> Result<Init, ParseRuleError> {
+    if let Some((_, column)) = parts.get(3) {
+        return Err(ParseRuleError::new(Field::Trailing, column - 1));
+    }
+    let part = |i: usize| parts.get(i).copied();
+    let size = part(0)
+        .map(|(size, column)| field(Field::InitSize, size, column, cube))
+        .transpose()?;
+    let density = part(1)
+        .map(|(density, column)| {
+            field(Field::InitDensity, density, column, density_parser)
+        })
+        .transpose()?;
+    let seed = part(2)
+        .map(|(seed, column)| {
+            field(Field::Seed, seed, column, character::complete::u64)
+        })
+        .transpose()?;
+    let (size, position) = size.unwrap_or((Init::default().size, None));
+    Ok(Init {
+        size,
+        density: density.unwrap_or(Init::default().density),
+        seed,
+        position,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_after_neighborhood_is_init_size() {
+        let (rule, init) = rule_and_init("S4/B4/M/20").unwrap();
+        assert_eq!(rule.max_state, 1);
+        assert!(matches!(rule.neighborhood, Neighborhood::Moore));
+        assert!(matches!(init.size, Extent::Cells(20)));
+        assert_eq!(rule.normalized().to_string(), "4/4/2/M");
+    }
 }
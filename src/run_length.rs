@@ -0,0 +1,61 @@
+//! Byte-wise run-length coding for cell grids.
+//!
+//! Each run is stored as the cell value followed by the run length as an
+//! LEB128 varint. Most grids are dominated by long runs of dead cells, so
+//! this typically shrinks a frame by one or two orders of magnitude.
+
+pub fn encode(cells: impl IntoIterator<Item = u8>) -> Vec<u8> {
+    let mut res = Vec::new();
+    let mut iter = cells.into_iter().peekable();
+    while let Some(value) = iter.next() {
+        let mut run = 1u64;
+        while iter.next_if_eq(&value).is_some() {
+            run += 1;
+        }
+        res.push(value);
+        write_varint(&mut res, run);
+    }
+    res
+}
+
+/// Decode `data` back into exactly `len` cells. Returns `None` if the data is
+/// truncated or does not add up to `len` cells.
+pub fn decode(data: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut res = Vec::with_capacity(len);
+    let mut pos = 0;
+    while pos < data.len() {
+        let value = data[pos];
+        pos += 1;
+        let run = usize::try_from(read_varint(data, &mut pos)?).ok()?;
+        if res.len().checked_add(run)? > len {
+            return None;
+        }
+        res.resize(res.len() + run, value);
+    }
+    (res.len() == len).then_some(res)
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        n |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+    }
+    None
+}
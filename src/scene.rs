@@ -0,0 +1,188 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Context;
+use cgmath::{InnerSpace, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{CameraPose, DEFAULT_FOVY},
+    game_of_life::SIZE,
+    palette::{self, Palette},
+    rule::Rule,
+    rule_file::RuleEntry,
+    rule_parse, Init,
+};
+
+/// Everything needed to reproduce a run of the viewer, stored as TOML.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scene {
+    pub rule: String,
+    /// Earlier rules with their initial cube, most recent last, which are
+    /// restored by undoing rule changes.
+    #[serde(default)]
+    pub undo: Vec<String>,
+    #[serde(default)]
+    pub grid: Grid,
+    /// The initial cube [default: the one of the rule string].
+    pub init: Option<Init>,
+    pub camera: Option<SceneCamera>,
+    #[serde(default)]
+    pub render: Render,
+    #[serde(default)]
+    pub simulation: Simulation,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Grid {
+    /// Edge length of the grid.
+    pub size: usize,
+    /// Overrides whether the neighborhood of the rule wraps around.
+    pub boundary: Option<Boundary>,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            size: SIZE,
+            boundary: None,
+        }
+    }
+}
+
+/// What lies beyond the faces of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundary {
+    /// The opposite face of the grid.
+    Wrap,
+    /// Dead cells.
+    Fixed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneCamera {
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    #[serde(default = "default_fovy")]
+    pub fovy: f32,
+}
+
+fn default_fovy() -> f32 {
+    DEFAULT_FOVY
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Render {
+    /// Hex colors from newborn to dying cells [default: gray scale].
+    pub palette: Option<Vec<String>>,
+    /// Hex color behind the cells.
+    pub background: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Simulation {
+    /// Generations per second [default: one per frame].
+    pub speed: Option<f32>,
+    /// Generation the scene shows, simulated from the initial cube on load.
+    pub generation: u64,
+    pub paused: bool,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            speed: None,
+            generation: 0,
+            paused: true,
+        }
+    }
+}
+
+impl Scene {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let scene: Self = toml::from_str(&text)
+            .with_context(|| format!("invalid scene {}", path.display()))?;
+        // Report mistakes right away rather than when they are used.
+        scene
+            .entry()
+            .and(scene.undo_rules())
+            .and(scene.background())
+            .with_context(|| format!("invalid scene {}", path.display()))?;
+        Ok(scene)
+    }
+
+    /// Write the scene to a new file at `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        file.write_all(toml::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// The rule, initial cube, camera pose and palette of the scene.
+    pub fn entry(&self) -> anyhow::Result<RuleEntry> {
+        let (mut rule, init) = parse_rule(&self.rule)?;
+        if let Some(boundary) = self.grid.boundary {
+            rule.neighborhood =
+                rule.neighborhood.with_wrapping(boundary == Boundary::Wrap);
+        }
+        let mut entry = RuleEntry::new("scene", rule, init);
+        if let Some(init) = &self.init {
+            entry.init = init.clone();
+        }
+        if let Some(camera) = &self.camera {
+            let dir = Vector3::from(camera.direction);
+            if dir == Vector3::zero() {
+                anyhow::bail!("the camera direction must not be zero");
+            }
+            entry.camera = Some(CameraPose {
+                pos: camera.position.into(),
+                dir: dir.normalize(),
+            });
+        }
+        if let Some(colors) = &self.render.palette {
+            entry.palette = Some(colors.join(",").parse::<Palette>()?);
+        }
+        Ok(entry)
+    }
+
+    /// The rules to undo to, with the seeds of their soups.
+    pub fn undo_rules(&self) -> anyhow::Result<Vec<(Rule, u64)>> {
+        self.undo
+            .iter()
+            .map(|r| {
+                let (rule, init) = parse_rule(r)?;
+                Ok((rule, init.seed.unwrap_or_default()))
+            })
+            .collect()
+    }
+
+    pub fn background(&self) -> anyhow::Result<Option<[u8; 3]>> {
+        self.render
+            .background
+            .as_deref()
+            .map(palette::parse_color)
+            .transpose()
+    }
+}
+
+fn parse_rule(rule: &str) -> anyhow::Result<(Rule, Init)> {
+    let mut rule_string = rule.to_string();
+    rule_string.retain(|c| !c.is_whitespace());
+    rule_parse::rule_and_init(&rule_string)
+        .with_context(|| format!("could not parse rule {rule}"))
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    ops::RangeInclusive,
+    path::Path,
+    time::Instant,
+};
+
+use anyhow::Context;
+use ndarray_rand::rand::{self, rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{
+    args::Args,
+    classify::{self, Outcome},
+    game_of_life::GameOfLife,
+    rule::Rule,
+    Init,
+};
+
+/// Default edge length of the grid rules are tested on.
+pub const DEFAULT_GRID_SIZE: usize = 32;
+
+/// The initial soup given by `--init-size` and `--init-density`, without a
+/// seed.
+pub fn soup(args: &Args) -> Init {
+    Init {
+        size: args.init_size.unwrap_or(Init::default().size),
+        density: args.init_density.unwrap_or(Init::default().density),
+        seed: None,
+        position: None,
+    }
+}
+
+/// Classify `count` random rules in parallel and write those with an outcome
+/// in `keep` to `output` as `.gol3d` files. With a `lambda` band, only rules
+/// whose Langton's lambda lies within it are tried.
+///
+/// Every rule starts from its own seeded soup, and both the rules and the
+/// soups are derived from `--seed`, so a search can be repeated exactly.
+pub fn run(
+    args: &Args,
+    count: usize,
+    generations: u64,
+    keep: &[Outcome],
+    lambda: Option<&RangeInclusive<f64>>,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let grid_size = args.grid_size.unwrap_or(DEFAULT_GRID_SIZE);
+    let init = soup(args);
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("Searching {count} rules with seed {seed}");
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let candidates = (0..count)
+        .map(|_| {
+            let rule = match lambda {
+                Some(band) => Rule::random_with_lambda(&mut rng, band)
+                    .with_context(|| {
+                        format!("found no rule with lambda in {band:?}")
+                    })?,
+                None => Rule::random_using(&mut rng),
+            };
+            let init = Init {
+                seed: Some(rng.gen()),
+                ..init.clone()
+            };
+            Ok((rule, init))
+        })
+        .collect::<anyhow::Result<Vec<(Rule, Init)>>>()?;
+
+    fs::create_dir_all(output)
+        .with_context(|| format!("could not create {}", output.display()))?;
+    let timer = Instant::now();
+    let results = candidates
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, (rule, init))| {
+            let mut gol = GameOfLife::new(rule, init, grid_size)?;
+            let classification = classify::classify(&mut gol, generations);
+            if !keep.contains(&classification.outcome) {
+                return Ok(classification.outcome);
+            }
+            println!(
+                "#{i}: {} ({}, soup seed {}) is {classification}",
+                gol.rule,
+                gol.rule.analysis(),
+                gol.init.seed.unwrap_or_default()
+            );
+            // Equivalent rules share a file, so that every rule is only
+            // saved once, across searches as well.
+            let path = output.join(format!(
+                "{}_{}.gol3d",
+                classification.outcome,
+                gol.rule.id()
+            ));
+            let file =
+                OpenOptions::new().write(true).create_new(true).open(&path);
+            match file {
+                Ok(mut file) => {
+                    write!(file, "{}/{}", gol.rule.normalized(), gol.init)
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    println!("#{i}: already saved as {}", path.display());
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+            .with_context(|| format!("could not write {}", path.display()))?;
+            Ok(classification.outcome)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut counts = BTreeMap::new();
+    for outcome in results {
+        *counts.entry(outcome).or_insert(0) += 1;
+    }
+    println!("Classified {count} rules in {:.2?}:", timer.elapsed());
+    for (outcome, n) in counts {
+        println!("  {outcome:>9}: {n}");
+    }
+    Ok(())
+}
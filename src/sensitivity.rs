@@ -0,0 +1,140 @@
+use ndarray::Array3;
+use ndarray_rand::rand;
+use rayon::prelude::*;
+
+use crate::{
+    args::Args, game_of_life::GameOfLife, rule::Rule,
+    search::DEFAULT_GRID_SIZE, Init,
+};
+
+/// Default number of generations after which the runs are compared.
+pub const DEFAULT_GENERATIONS: u64 = 100;
+
+/// A survive or born bit of a rule.
+#[derive(Debug, Clone, Copy)]
+pub enum Bit {
+    Survive(u8),
+    Born(u8),
+}
+
+impl std::fmt::Display for Bit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Bit::Survive(n) => format!("S{n}"),
+            Bit::Born(n) => format!("B{n}"),
+        };
+        f.pad(&name)
+    }
+}
+
+impl Bit {
+    fn is_set(&self, rule: &Rule) -> bool {
+        match self {
+            Bit::Survive(n) => rule.survive(*n),
+            Bit::Born(n) => rule.born(*n),
+        }
+    }
+
+    fn toggle(&self, rule: &mut Rule) {
+        match self {
+            Bit::Survive(n) => rule.survive_mask ^= 1 << n,
+            Bit::Born(n) => rule.born_mask ^= 1 << n,
+        }
+    }
+}
+
+/// How far a run with one bit toggled ended up from the baseline.
+pub struct Divergence {
+    pub bit: Bit,
+    /// Whether the bit was set in the original rule.
+    pub was_set: bool,
+    /// Number of cells whose state differs from the baseline.
+    pub hamming: usize,
+    pub population_delta: i64,
+}
+
+fn simulate(
+    rule: Rule,
+    init: Init,
+    size: usize,
+    generations: u64,
+) -> anyhow::Result<Array3<u8>> {
+    let mut gol = GameOfLife::new(rule, init, size)?;
+    while gol.generation < generations {
+        gol.update();
+    }
+    Ok(gol.cells)
+}
+
+fn population(cells: &Array3<u8>) -> i64 {
+    cells.iter().filter(|c| **c != 0).count() as i64
+}
+
+/// Toggle every survive and born bit the neighborhood can reach, one at a
+/// time, and compare each run against the unchanged rule after
+/// `generations` generations. The most disruptive bits come first.
+pub fn analyze(
+    rule: &Rule,
+    init: &Init,
+    size: usize,
+    generations: u64,
+) -> anyhow::Result<Vec<Divergence>> {
+    let baseline = simulate(rule.clone(), init.clone(), size, generations)?;
+    let bits: Vec<_> = (0..=rule.neighborhood.size())
+        .flat_map(|n| [Bit::Survive(n), Bit::Born(n)])
+        .collect();
+    let mut divergences: Vec<_> = bits
+        .into_par_iter()
+        .map(|bit| {
+            let mut toggled = rule.clone();
+            bit.toggle(&mut toggled);
+            let cells = simulate(toggled, init.clone(), size, generations)?;
+            Ok(Divergence {
+                bit,
+                was_set: bit.is_set(rule),
+                hamming: baseline
+                    .iter()
+                    .zip(cells.iter())
+                    .filter(|(a, b)| a != b)
+                    .count(),
+                population_delta: population(&cells) - population(&baseline),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    divergences.sort_by_key(|d| std::cmp::Reverse(d.hamming));
+    Ok(divergences)
+}
+
+/// Print the ranked sensitivity of the rule given by `args`.
+pub fn run(args: &Args, generations: u64) -> anyhow::Result<()> {
+    let (rule, mut init) = args.rule_and_init()?;
+    // Every run has to start from the same soup.
+    let seed = *init.seed.get_or_insert_with(rand::random);
+    let size = args.grid_size.unwrap_or(DEFAULT_GRID_SIZE);
+    println!("Rule: {rule} ({})", rule.analysis());
+    println!("Soup seed {seed}, {generations} generations on {size}^3");
+
+    let divergences = analyze(&rule, &init, size, generations)?;
+    let essential = divergences.iter().filter(|d| d.hamming > 0).count();
+    let cells = size.pow(3) as f64;
+    println!(
+        "{:>4} {:>4} {:>6} {:>10} {:>8} {:>12}",
+        "rank", "bit", "was", "hamming", "ratio", "population"
+    );
+    for (rank, d) in divergences.iter().enumerate() {
+        println!(
+            "{:>4} {:>4} {:>6} {:>10} {:>8.4} {:>+12}",
+            rank + 1,
+            d.bit,
+            if d.was_set { "on" } else { "off" },
+            d.hamming,
+            d.hamming as f64 / cells,
+            d.population_delta
+        );
+    }
+    println!(
+        "{essential} of {} bits change the outcome",
+        divergences.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,118 @@
+//! Binary snapshots of a grid, see [`GameOfLife::save`].
+//!
+//! All numbers are little endian:
+//!
+//! | Bytes | Content                                                   |
+//! | ----- | --------------------------------------------------------- |
+//! | 8     | `GOL3DSNP`                                                |
+//! | 2     | Format version                                            |
+//! | 3 × 4 | Dimensions                                                |
+//! | 8     | Generation                                                |
+//! | 1 + 8 | Whether there is a seed, and the seed of the initial soup |
+//! | 4 + n | Length and UTF-8 of the rule string with its initial cube |
+//! | 4 + n | Length of the cells and the cells, see `run_length.rs`    |
+
+use std::io::{Read, Write};
+
+use anyhow::{bail, Context};
+use ndarray::Array3;
+
+use crate::{
+    game_of_life::{GameOfLife, MAX_SIZE},
+    rule_parse, run_length, Init,
+};
+
+const MAGIC: &[u8; 8] = b"GOL3DSNP";
+const VERSION: u16 = 2;
+/// Longest rule string read.
+const MAX_RULE_LEN: usize = 1024;
+/// File extension of snapshots.
+pub const EXTENSION: &str = "gol3dsnap";
+
+pub fn write(gol: &GameOfLife, mut w: impl Write) -> anyhow::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    let (x, y, z) = gol.cells.dim();
+    for d in [x, y, z] {
+        w.write_all(&u32::try_from(d)?.to_le_bytes())?;
+    }
+    w.write_all(&gol.generation.to_le_bytes())?;
+    w.write_all(&[gol.init.seed.is_some() as u8])?;
+    w.write_all(&gol.init.seed.unwrap_or_default().to_le_bytes())?;
+    let init = Init {
+        seed: None,
+        ..gol.init.clone()
+    };
+    let rule = format!("{}/{init}", gol.rule);
+    w.write_all(&u32::try_from(rule.len())?.to_le_bytes())?;
+    w.write_all(rule.as_bytes())?;
+
+    let cells = run_length::encode(gol.cells.iter().copied());
+    w.write_all(&u32::try_from(cells.len())?.to_le_bytes())?;
+    w.write_all(&cells)?;
+    Ok(())
+}
+
+pub fn read(mut r: impl Read) -> anyhow::Result<GameOfLife> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic).context("not a snapshot")?;
+    if &magic != MAGIC {
+        bail!("not a snapshot");
+    }
+    let version = u16::from_le_bytes(read_array(&mut r)?);
+    if version != VERSION {
+        bail!("unsupported snapshot version {version}");
+    }
+    let mut dim = [0; 3];
+    for d in &mut dim {
+        *d = u32::from_le_bytes(read_array(&mut r)?) as usize;
+        if *d > MAX_SIZE {
+            bail!("the grid is larger than {MAX_SIZE} cells along an axis");
+        }
+    }
+    let generation = u64::from_le_bytes(read_array(&mut r)?);
+    let [has_seed] = read_array(&mut r)?;
+    let seed = u64::from_le_bytes(read_array(&mut r)?);
+    let rule = read_bytes(&mut r, MAX_RULE_LEN).context("invalid rule")?;
+    let rule = String::from_utf8(rule)?;
+    let (rule, mut init) = rule_parse::rule_and_init(&rule)
+        .with_context(|| format!("invalid rule {rule}"))?;
+    init.seed = (has_seed != 0).then_some(seed);
+
+    let count = dim
+        .iter()
+        .try_fold(1usize, |n, d| n.checked_mul(*d))
+        .context("corrupt dimensions")?;
+    // A run of n cells takes at most 2n bytes.
+    let cells = read_bytes(&mut r, 2 * count).context("corrupt cells")?;
+    let cells = run_length::decode(&cells, count).context("corrupt cells")?;
+    if cells.iter().any(|&state| state > rule.max_state) {
+        bail!("corrupt cells");
+    }
+    Ok(GameOfLife {
+        cells: Array3::from_shape_vec((dim[0], dim[1], dim[2]), cells)?,
+        rule,
+        init,
+        generation,
+    })
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> anyhow::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    r.read_exact(&mut bytes).context("truncated snapshot")?;
+    Ok(bytes)
+}
+
+/// A length as 4 bytes, up to `max`, followed by that many bytes.
+fn read_bytes(r: &mut impl Read, max: usize) -> anyhow::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_array(r)?) as usize;
+    if len > max {
+        bail!("{len} bytes are more than the {max} expected");
+    }
+    let mut bytes = Vec::new();
+    r.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        bail!("truncated snapshot");
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+use ndarray::Array3;
+
+use crate::{
+    components::{self, Connectivity},
+    game_of_life::GameOfLife,
+};
+
+/// Default number of generations simulated by the `gliders` command.
+pub const DEFAULT_GENERATIONS: u64 = 500;
+/// Default longest period looked for.
+pub const DEFAULT_MAX_PERIOD: u64 = 30;
+
+/// An object that reappears with the same shape at a displaced position.
+pub struct Spaceship {
+    pub period: u64,
+    pub displacement: [i64; 3],
+    /// Generation in which the object was recognized.
+    pub generation: u64,
+    /// Lower corner of the bounding box in that generation.
+    pub position: [usize; 3],
+    pub shape: Array3<u8>,
+}
+
+impl Spaceship {
+    /// The speed in the usual `(dx,dy,dz)c/p` notation, reduced by the
+    /// greatest common divisor.
+    pub fn speed(&self) -> String {
+        let [dx, dy, dz] = self.displacement;
+        let divisor = [dx, dy, dz]
+            .into_iter()
+            .fold(self.period as i64, |acc, d| gcd(acc, d.abs()));
+        format!(
+            "({},{},{})c/{}",
+            dx / divisor,
+            dy / divisor,
+            dz / divisor,
+            self.period as i64 / divisor
+        )
+    }
+}
+
+impl Display for Spaceship {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let dim = self.shape.dim();
+        write!(
+            f,
+            "{} (period {}, {} cells in {}x{}x{}) at {:?} in gen {}",
+            self.speed(),
+            self.period,
+            self.shape.iter().filter(|c| **c != 0).count(),
+            dim.0,
+            dim.1,
+            dim.2,
+            self.position,
+            self.generation
+        )
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Finds spaceships by comparing every object against the objects of the
+/// last `2 * max_period` generations.
+///
+/// An object counts as a spaceship of period `p` if objects of identical shape
+/// (including cell states) were seen `p` and `2p` generations earlier, each
+/// time offset by the same displacement of no more than `p` cells along each
+/// axis.
+///
+/// Each kind of spaceship is reported only once. The other phases of a
+/// spaceship are recognized as belonging to it because they are matched with
+/// the same velocity right next to where it was matched in the previous
+/// generation.
+pub struct SpaceshipDetector {
+    max_period: u64,
+    recent: VecDeque<HashMap<u64, Vec<[usize; 3]>>>,
+    /// Known phases, keyed by shape, period and displacement.
+    phases: HashMap<(u64, u64, [i64; 3]), usize>,
+    /// Spaceships matched in the previous generation.
+    previous: Vec<Match>,
+    num_kinds: usize,
+}
+
+struct Match {
+    kind: usize,
+    period: u64,
+    displacement: [i64; 3],
+    position: [usize; 3],
+}
+
+impl SpaceshipDetector {
+    pub fn new(max_period: u64) -> Self {
+        Self {
+            max_period,
+            recent: VecDeque::with_capacity(2 * max_period as usize),
+            phases: HashMap::new(),
+            previous: Vec::new(),
+            num_kinds: 0,
+        }
+    }
+
+    /// Look at the current generation of `gol` and return the spaceships
+    /// that were recognized for the first time.
+    pub fn observe(&mut self, gol: &GameOfLife) -> Vec<Spaceship> {
+        let mut found = Vec::new();
+        let mut matches = Vec::new();
+        let mut shapes: HashMap<u64, Vec<[usize; 3]>> = HashMap::new();
+        for component in components::label(&gol.cells, Connectivity::Full) {
+            let shape = component.crop(&gol.cells);
+            let hash = shape_hash(&shape);
+            let pos = component.min;
+            shapes.entry(hash).or_default().push(pos);
+
+            // `recent` is ordered from the previous generation backwards.
+            let matched = (1..=self.max_period).find_map(|p| {
+                self.seen(hash, p)?.iter().find_map(|old_pos| {
+                    let offset = displacement(*old_pos, pos);
+                    let moved = offset.iter().any(|d| *d != 0);
+                    let reachable =
+                        offset.iter().all(|d| d.unsigned_abs() <= p);
+                    // Require a second period at the same velocity to rule
+                    // out chance encounters of identical debris.
+                    let confirmed = self.seen(hash, 2 * p).is_some_and(|o| {
+                        o.iter().any(|o| displacement(*o, *old_pos) == offset)
+                    });
+                    (moved && reachable && confirmed).then_some((p, offset))
+                })
+            });
+            let Some((period, displacement)) = matched else {
+                continue;
+            };
+            let reach = shape.shape().iter().copied().max().unwrap_or(1);
+            let kind = match self.phases.get(&(hash, period, displacement)) {
+                Some(kind) => *kind,
+                None => {
+                    // Compare velocities rather than periods, since a ship
+                    // whose phases repeat only approximately may be matched
+                    // at a multiple of its period.
+                    let known = self.previous.iter().find(|m| {
+                        (0..3).all(|i| {
+                            m.displacement[i] * period as i64
+                                == displacement[i] * m.period as i64
+                        }) && (0..3)
+                            .all(|i| m.position[i].abs_diff(pos[i]) <= reach)
+                    });
+                    let kind = match known {
+                        Some(m) => m.kind,
+                        None => {
+                            self.num_kinds += 1;
+                            found.push(Spaceship {
+                                period,
+                                displacement,
+                                generation: gol.generation,
+                                position: pos,
+                                shape,
+                            });
+                            self.num_kinds - 1
+                        }
+                    };
+                    self.phases.insert((hash, period, displacement), kind);
+                    kind
+                }
+            };
+            matches.push(Match {
+                kind,
+                period,
+                displacement,
+                position: pos,
+            });
+        }
+
+        self.previous = matches;
+        self.recent.push_front(shapes);
+        self.recent.truncate(2 * self.max_period as usize);
+        found
+    }
+
+    /// Positions of objects with shape `hash` seen `age` generations ago.
+    fn seen(&self, hash: u64, age: u64) -> Option<&Vec<[usize; 3]>> {
+        self.recent.get(age as usize - 1)?.get(&hash)
+    }
+}
+
+/// Simulate `gol` and print every kind of spaceship as it is found.
+pub fn run(mut gol: GameOfLife, generations: u64, max_period: u64) {
+    println!("Rule: {}", gol.rule);
+    let mut detector = SpaceshipDetector::new(max_period);
+    let mut count = 0;
+    loop {
+        for spaceship in detector.observe(&gol) {
+            println!("{spaceship}");
+            count += 1;
+        }
+        if gol.generation >= generations {
+            break;
+        }
+        gol.update();
+    }
+    println!("Found {count} kind(s) of spaceship in {generations} generations");
+}
+
+fn displacement(from: [usize; 3], to: [usize; 3]) -> [i64; 3] {
+    [0, 1, 2].map(|i| to[i] as i64 - from[i] as i64)
+}
+
+fn shape_hash(shape: &Array3<u8>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    shape.dim().hash(&mut hasher);
+    shape.iter().for_each(|c| c.hash(&mut hasher));
+    hasher.finish()
+}
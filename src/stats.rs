@@ -0,0 +1,216 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use ndarray::Array3;
+use serde::Serialize;
+
+use crate::{game_of_life::GameOfLife, rule::MAX_STATES};
+
+/// Metrics of a single generation.
+///
+/// Births count cells that went from dead to alive since the previous
+/// generation, deaths count cells that went from alive (any state) to dead.
+/// `density` is the population divided by the number of cells in the grid.
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationStats {
+    pub generation: u64,
+    pub population: usize,
+    /// Number of cells in each state, starting with state 1.
+    pub states: Vec<usize>,
+    pub births: usize,
+    pub deaths: usize,
+    pub bounding_box: Option<[[usize; 3]; 2]>,
+    pub centroid: Option<[f64; 3]>,
+    pub radius_of_gyration: Option<f64>,
+    pub density: f64,
+}
+
+impl GenerationStats {
+    pub fn collect(gol: &GameOfLife, previous: Option<&Array3<u8>>) -> Self {
+        let mut states = vec![0; gol.rule.max_state as usize];
+        let mut min = [usize::MAX; 3];
+        let mut max = [0; 3];
+        let mut sum = [0.; 3];
+        let mut sum_sq = 0.;
+        for ((x, y, z), c) in gol.cells.indexed_iter() {
+            if *c == 0 {
+                continue;
+            }
+            if let Some(n) = states.get_mut(*c as usize - 1) {
+                *n += 1;
+            }
+            for (axis, v) in [x, y, z].into_iter().enumerate() {
+                min[axis] = min[axis].min(v);
+                max[axis] = max[axis].max(v);
+                sum[axis] += v as f64;
+                sum_sq += (v * v) as f64;
+            }
+        }
+
+        let (mut births, mut deaths) = (0, 0);
+        if let Some(previous) = previous.filter(|p| p.dim() == gol.cells.dim())
+        {
+            for (old, new) in previous.iter().zip(gol.cells.iter()) {
+                match (*old, *new) {
+                    (0, n) if n != 0 => births += 1,
+                    (o, 0) if o != 0 => deaths += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let population = gol.cells.iter().filter(|c| **c != 0).count();
+        let (bounding_box, centroid, radius_of_gyration) = if population == 0 {
+            (None, None, None)
+        } else {
+            let n = population as f64;
+            let centroid = sum.map(|s| s / n);
+            let mean_sq =
+                sum_sq / n - centroid.iter().map(|c| c * c).sum::<f64>();
+            (
+                Some([min, max]),
+                Some(centroid),
+                Some(mean_sq.max(0.).sqrt()),
+            )
+        };
+
+        Self {
+            generation: gol.generation,
+            population,
+            states,
+            births,
+            deaths,
+            bounding_box,
+            centroid,
+            radius_of_gyration,
+            density: population as f64 / gol.cells.len() as f64,
+        }
+    }
+
+    fn csv_header(num_states: usize) -> String {
+        let mut header = String::from(
+            "generation,population,births,deaths,density,\
+             min_x,min_y,min_z,max_x,max_y,max_z,\
+             centroid_x,centroid_y,centroid_z,radius_of_gyration",
+        );
+        for i in 1..=num_states {
+            header += &format!(",state_{i}");
+        }
+        header
+    }
+
+    /// The row with `num_states` state columns, padded with zeros for states
+    /// the rule does not have.
+    fn csv_row(&self, num_states: usize) -> String {
+        let mut row = format!(
+            "{},{},{},{},{}",
+            self.generation,
+            self.population,
+            self.births,
+            self.deaths,
+            self.density
+        );
+        match self.bounding_box {
+            Some([min, max]) => {
+                for v in min.iter().chain(max.iter()) {
+                    row += &format!(",{v}");
+                }
+            }
+            None => row += ",,,,,,",
+        }
+        match self.centroid {
+            Some(c) => row += &format!(",{},{},{}", c[0], c[1], c[2]),
+            None => row += ",,,",
+        }
+        match self.radius_of_gyration {
+            Some(r) => row += &format!(",{r}"),
+            None => row += ",",
+        }
+        for i in 0..num_states {
+            row += &format!(",{}", self.states.get(i).unwrap_or(&0));
+        }
+        row
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Csv,
+    JsonLines,
+}
+
+impl StatsFormat {
+    /// `.json` and `.jsonl` files get JSON lines, anything else CSV.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json" | "jsonl") => StatsFormat::JsonLines,
+            _ => StatsFormat::Csv,
+        }
+    }
+}
+
+/// Writes one [`GenerationStats`] record per observed generation.
+pub struct StatsWriter {
+    out: BufWriter<File>,
+    format: StatsFormat,
+    previous: Option<Array3<u8>>,
+    num_states: Option<usize>,
+    /// Whether a rule had more states than the CSV header.
+    truncated: bool,
+}
+
+impl StatsWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            format: StatsFormat::from_path(path),
+            previous: None,
+            num_states: None,
+            truncated: false,
+        })
+    }
+
+    /// Forget the previous generation, so that the next record does not
+    /// count births and deaths across a reset.
+    pub fn restart(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn record(&mut self, gol: &GameOfLife) -> io::Result<()> {
+        let stats = GenerationStats::collect(gol, self.previous.as_ref());
+        match self.format {
+            StatsFormat::Csv => {
+                // The header has room for every state the rule can be
+                // changed to while running, so that it is written once.
+                let num_states = match self.num_states {
+                    Some(n) => n,
+                    None => {
+                        let n = stats.states.len().max(MAX_STATES as usize - 1);
+                        let header = GenerationStats::csv_header(n);
+                        writeln!(self.out, "{header}")?;
+                        self.num_states = Some(n);
+                        n
+                    }
+                };
+                if stats.states.len() > num_states && !self.truncated {
+                    self.truncated = true;
+                    log::warn!(
+                        "The statistics only have columns for {num_states} \
+                         states, leaving out the others"
+                    );
+                }
+                writeln!(self.out, "{}", stats.csv_row(num_states))?;
+            }
+            StatsFormat::JsonLines => {
+                serde_json::to_writer(&mut self.out, &stats)?;
+                writeln!(self.out)?;
+            }
+        }
+        self.out.flush()?;
+        self.previous = Some(gol.cells.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use ndarray_rand::rand;
+use rayon::prelude::*;
+
+use crate::{
+    args::Args,
+    classify::{self, Classification, Outcome},
+    game_of_life::GameOfLife,
+    search::DEFAULT_GRID_SIZE,
+    Extent, Init,
+};
+
+/// Default number of generations of every run.
+pub const DEFAULT_GENERATIONS: u64 = 200;
+
+/// One run of the sweep.
+pub struct SweepRun {
+    pub size: Extent,
+    pub density: f64,
+    pub seed: u64,
+    pub classification: Classification,
+}
+
+/// Run the rule given by `args` from every combination of initial cube
+/// `sizes` and `densities`, each with `seeds` different soups, and print a
+/// table of the results. With `output`, every single run is also written as
+/// CSV.
+pub fn run(
+    args: &Args,
+    sizes: &[Extent],
+    densities: &[f64],
+    seeds: u64,
+    generations: u64,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(seeds > 0, "at least one seed is needed");
+    let (rule, _) = args.rule_and_init()?;
+    let grid_size = args.grid_size.unwrap_or(DEFAULT_GRID_SIZE);
+    let base_seed = args.seed.unwrap_or_else(rand::random);
+    println!("Rule: {rule} ({})", rule.analysis());
+    println!("Seeds {base_seed} to {}", base_seed.wrapping_add(seeds - 1));
+
+    let mut params = Vec::new();
+    for &size in sizes {
+        for &density in densities {
+            for i in 0..seeds {
+                params.push((size, density, base_seed.wrapping_add(i)));
+            }
+        }
+    }
+    let runs: Vec<SweepRun> = params
+        .into_par_iter()
+        .map(|(size, density, seed)| {
+            let init = Init {
+                size,
+                density,
+                seed: Some(seed),
+                position: None,
+            };
+            let mut gol = GameOfLife::new(rule.clone(), init, grid_size)?;
+            Ok(SweepRun {
+                size,
+                density,
+                seed,
+                classification: classify::classify(&mut gol, generations),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    println!(
+        "{:>5} {:>8} {:>12} {:>10}  outcomes",
+        "size", "density", "population", "lifetime"
+    );
+    for chunk in runs.chunks(seeds as usize) {
+        let n = chunk.len() as f64;
+        let population = chunk
+            .iter()
+            .map(|r| r.classification.population as f64)
+            .sum::<f64>()
+            / n;
+        let lifetime = chunk
+            .iter()
+            .map(|r| r.classification.lifetime() as f64)
+            .sum::<f64>()
+            / n;
+        let mut outcomes: BTreeMap<Outcome, usize> = BTreeMap::new();
+        for r in chunk {
+            *outcomes.entry(r.classification.outcome).or_default() += 1;
+        }
+        let outcomes: Vec<_> =
+            outcomes.iter().map(|(o, n)| format!("{n} {o}")).collect();
+        println!(
+            "{:>5} {:>8} {population:>12.1} {lifetime:>10.1}  {}",
+            chunk[0].size,
+            chunk[0].density,
+            outcomes.join(", ")
+        );
+    }
+
+    if let Some(path) = output {
+        let file = File::create(path)
+            .with_context(|| format!("could not create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "size,density,seed,outcome,population,lifetime")?;
+        for r in &runs {
+            writeln!(
+                out,
+                "{},{},{},{},{},{}",
+                r.size,
+                r.density,
+                r.seed,
+                r.classification.outcome,
+                r.classification.population,
+                r.classification.lifetime()
+            )?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
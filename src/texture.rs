@@ -4,6 +4,7 @@ use wgpu::{
     TextureViewDescriptor,
 };
 pub struct Texture {
+    #[allow(dead_code)]
     pub texture: wgpu::Texture,
     pub view: TextureView,
 }
@@ -0,0 +1,398 @@
+//! MagicaVoxel `.vox` files, see
+//! <https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt>
+//! and `MagicaVoxel-file-format-vox-extension.txt` for the scene graph.
+//!
+//! The palette index of a voxel is its state as in a [`Pattern`], so newborn
+//! cells have index 1. Grids larger than the 256 voxels a model can hold
+//! along each axis are split into several models, which the scene graph puts
+//! side by side. MagicaVoxel's z points up like the y of the viewer.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{bail, Context};
+use ndarray::Array3;
+
+use crate::{
+    game_of_life::MAX_SIZE,
+    palette::{self, Palette},
+    pattern::{Orientation, Pattern},
+};
+
+/// File extension of MagicaVoxel files.
+pub const EXTENSION: &str = "vox";
+const VERSION: i32 = 150;
+/// Largest edge length of a model.
+const MAX_MODEL_SIZE: usize = 256;
+/// Turns the axes of the grid into those of MagicaVoxel.
+const TO_VOX: Orientation = Orientation {
+    axes: [0, 2, 1],
+    flip: [false, true, false],
+};
+/// Turns the axes of MagicaVoxel into those of the grid.
+const FROM_VOX: Orientation = Orientation {
+    axes: [0, 2, 1],
+    flip: [false, false, true],
+};
+
+/// Write `pattern` with the colors of `palette`, or the gray scale of the
+/// viewer.
+pub fn write(
+    pattern: &Pattern,
+    palette: Option<&Palette>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let pattern = pattern.turned(TO_VOX);
+    let cells = &pattern.cells;
+    let dim = <[usize; 3]>::from(cells.dim());
+
+    // Split the pattern into models, skipping empty ones.
+    let mut models = Vec::new();
+    let blocks = dim.map(|d| d.div_ceil(MAX_MODEL_SIZE));
+    for i in 0..blocks[0] * blocks[1] * blocks[2] {
+        let block = [
+            i % blocks[0],
+            i / blocks[0] % blocks[1],
+            i / blocks[0] / blocks[1],
+        ];
+        let min = block.map(|n| n * MAX_MODEL_SIZE);
+        let size = [0, 1, 2].map(|a| (dim[a] - min[a]).min(MAX_MODEL_SIZE));
+        let block = cells.slice(ndarray::s![
+            min[0]..min[0] + size[0],
+            min[1]..min[1] + size[1],
+            min[2]..min[2] + size[2],
+        ]);
+        let voxels: Vec<[u8; 4]> = block
+            .indexed_iter()
+            .filter(|(_, &state)| state != 0)
+            .map(|((x, y, z), &state)| [x as u8, y as u8, z as u8, state])
+            .collect();
+        if !voxels.is_empty() {
+            models.push((min, size, voxels));
+        }
+    }
+    if models.is_empty() {
+        models.push((
+            [0; 3],
+            dim.map(|d| d.clamp(1, MAX_MODEL_SIZE)),
+            Vec::new(),
+        ));
+    }
+
+    let mut children = Vec::new();
+    for (_, size, voxels) in &models {
+        let mut content = Vec::new();
+        for s in size {
+            content.extend((*s as i32).to_le_bytes());
+        }
+        children.extend(chunk(b"SIZE", &content));
+        let mut content = (voxels.len() as i32).to_le_bytes().to_vec();
+        content.extend(voxels.iter().flatten());
+        children.extend(chunk(b"XYZI", &content));
+    }
+
+    // A root transform with a group of one transform and shape per model.
+    let mut content = node_header(0);
+    content.extend(transform(1, -1, None));
+    children.extend(chunk(b"nTRN", &content));
+    let mut content = node_header(1);
+    content.extend((models.len() as i32).to_le_bytes());
+    for i in 0..models.len() {
+        content.extend((2 + 2 * i as i32).to_le_bytes());
+    }
+    children.extend(chunk(b"nGRP", &content));
+    for (i, (min, size, _)) in models.iter().enumerate() {
+        let id = 2 + 2 * i as i32;
+        // Models are positioned by their center, rounded down.
+        let center = [0, 1, 2].map(|a| (min[a] + size[a] / 2) as i32);
+        let mut content = node_header(id);
+        content.extend(transform(id + 1, 0, Some(center)));
+        children.extend(chunk(b"nTRN", &content));
+        let mut content = node_header(id + 1);
+        content.extend(1i32.to_le_bytes());
+        content.extend((i as i32).to_le_bytes());
+        content.extend(dict(&[]));
+        children.extend(chunk(b"nSHP", &content));
+    }
+
+    let max_state = match &pattern.rule {
+        Some(rule) => rule.max_state,
+        None => cells.iter().copied().max().unwrap_or(0),
+    };
+    let mut content = Vec::new();
+    for index in 1..=u8::MAX {
+        let color = match index <= max_state {
+            true => {
+                palette::state_color(palette, max_state + 1 - index, max_state)
+            }
+            false => [0; 3],
+        };
+        content.extend(color);
+        content.push(u8::MAX);
+    }
+    // The palette has 256 entries, of which the last one is unused.
+    content.extend([0; 4]);
+    children.extend(chunk(b"RGBA", &content));
+
+    let mut bytes = b"VOX ".to_vec();
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend(b"MAIN");
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend((children.len() as i32).to_le_bytes());
+    bytes.extend(children);
+    fs::write(path, bytes)
+        .with_context(|| format!("could not write {}", path.display()))
+}
+
+/// The models of a `.vox` file, put together where the scene graph places
+/// them, as a pattern in which every palette index is a state.
+pub fn read(path: &Path) -> anyhow::Result<Pattern> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    parse(&bytes)
+        .with_context(|| format!("invalid MagicaVoxel file {}", path.display()))
+}
+
+/// A node of the scene graph.
+enum Node {
+    Transform { child: i32, translation: [i32; 3] },
+    Group { children: Vec<i32> },
+    Shape { model: i32 },
+}
+
+fn parse(bytes: &[u8]) -> anyhow::Result<Pattern> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(4)? != b"VOX " {
+        bail!("not a MagicaVoxel file");
+    }
+    r.i32()?;
+    if r.take(4)? != b"MAIN" {
+        bail!("expected the MAIN chunk");
+    }
+    let content = r.len()?;
+    r.take(content)?;
+    let end = r.pos.checked_add(r.len()?).context("invalid MAIN chunk")?;
+
+    let mut sizes = Vec::new();
+    let mut models = Vec::new();
+    let mut nodes = HashMap::new();
+    while r.pos < end {
+        let id: [u8; 4] = r.take(4)?.try_into().unwrap();
+        let len = r.len()?;
+        let children = r.len()?;
+        let mut c = Reader {
+            bytes: r.take(len)?,
+            pos: 0,
+        };
+        r.take(children)?;
+        match &id {
+            b"SIZE" => sizes.push([c.i32()?, c.i32()?, c.i32()?]),
+            b"XYZI" => {
+                let n = c.len()?;
+                let voxels =
+                    c.take(n.checked_mul(4).context("too many voxels")?)?;
+                models.push(
+                    voxels
+                        .chunks(4)
+                        .map(|v| [v[0], v[1], v[2], v[3]])
+                        .collect::<Vec<_>>(),
+                );
+            }
+            b"nTRN" => {
+                let node = c.i32()?;
+                c.dict()?;
+                let child = c.i32()?;
+                c.i32()?;
+                c.i32()?;
+                let frames = c.i32()?;
+                let mut translation = [0; 3];
+                for _ in 0..frames {
+                    let frame = c.dict()?;
+                    if frame.contains_key("_r") {
+                        log::warn!("rotated models are read without rotation");
+                    }
+                    if let Some(t) = frame.get("_t") {
+                        let t: Vec<i32> = t
+                            .split_whitespace()
+                            .map(str::parse)
+                            .collect::<Result<_, _>>()?;
+                        translation = t.try_into().map_err(|_| {
+                            anyhow::anyhow!(
+                                "expected a translation like `1 2 3`"
+                            )
+                        })?;
+                    }
+                }
+                nodes.insert(node, Node::Transform { child, translation });
+            }
+            b"nGRP" => {
+                let node = c.i32()?;
+                c.dict()?;
+                let n = c.i32()?;
+                let children =
+                    (0..n).map(|_| c.i32()).collect::<anyhow::Result<_>>()?;
+                nodes.insert(node, Node::Group { children });
+            }
+            b"nSHP" => {
+                let node = c.i32()?;
+                c.dict()?;
+                c.i32()?;
+                let model = c.i32()?;
+                nodes.insert(node, Node::Shape { model });
+            }
+            _ => {}
+        }
+    }
+    if sizes.len() != models.len() || models.is_empty() {
+        bail!("expected a SIZE and XYZI chunk for every model");
+    }
+
+    // The lowest corner of every model, from the scene graph if there is one.
+    let mut placed = Vec::new();
+    if nodes.is_empty() {
+        placed.extend((0..models.len()).map(|model| (model, [0; 3])));
+    } else {
+        visit(&nodes, 0, [0; 3], &mut placed, 0)?;
+    }
+    let mut voxels = Vec::new();
+    for (model, translation) in placed {
+        let size = sizes.get(model).context("unknown model")?;
+        for &[x, y, z, index] in &models[model] {
+            let local = [x, y, z];
+            // Wide enough that no i32 of the file can overflow.
+            let world = [0, 1, 2].map(|a| {
+                translation[a] as i64 - size[a] as i64 / 2 + local[a] as i64
+            });
+            voxels.push((world, index));
+        }
+    }
+    let min =
+        [0, 1, 2].map(|a| voxels.iter().map(|(p, _)| p[a]).min().unwrap_or(0));
+    let max =
+        [0, 1, 2].map(|a| voxels.iter().map(|(p, _)| p[a]).max().unwrap_or(0));
+    if (0..3).any(|a| max[a] - min[a] >= MAX_SIZE as i64) {
+        bail!("the models span more than {MAX_SIZE} voxels along an axis");
+    }
+    let dim = [0, 1, 2].map(|a| (max[a] - min[a] + 1) as usize);
+    let mut cells = Array3::zeros((dim[0], dim[1], dim[2]));
+    for (p, index) in voxels {
+        cells[[0, 1, 2].map(|a| (p[a] - min[a]) as usize)] = index;
+    }
+    Ok(Pattern {
+        cells,
+        rule: None,
+        comments: Vec::new(),
+    }
+    .turned(FROM_VOX))
+}
+
+/// Collect the models below `node` with their translation.
+fn visit(
+    nodes: &HashMap<i32, Node>,
+    node: i32,
+    offset: [i32; 3],
+    placed: &mut Vec<(usize, [i32; 3])>,
+    depth: usize,
+) -> anyhow::Result<()> {
+    if depth > nodes.len() {
+        bail!("the scene graph has a cycle");
+    }
+    match nodes.get(&node).context("unknown node")? {
+        Node::Transform { child, translation } => {
+            let mut offset = offset;
+            for (o, t) in offset.iter_mut().zip(translation) {
+                *o = o
+                    .checked_add(*t)
+                    .context("the translation is too large")?;
+            }
+            visit(nodes, *child, offset, placed, depth + 1)?;
+        }
+        Node::Group { children } => {
+            for child in children {
+                visit(nodes, *child, offset, placed, depth + 1)?;
+            }
+        }
+        Node::Shape { model } => placed.push((*model as usize, offset)),
+    }
+    Ok(())
+}
+
+fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend((content.len() as i32).to_le_bytes());
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend(content);
+    bytes
+}
+
+/// The node id and its empty attributes.
+fn node_header(id: i32) -> Vec<u8> {
+    let mut bytes = id.to_le_bytes().to_vec();
+    bytes.extend(dict(&[]));
+    bytes
+}
+
+/// The rest of a transform node with a single frame.
+fn transform(child: i32, layer: i32, translation: Option<[i32; 3]>) -> Vec<u8> {
+    let mut bytes = child.to_le_bytes().to_vec();
+    bytes.extend((-1i32).to_le_bytes());
+    bytes.extend(layer.to_le_bytes());
+    bytes.extend(1i32.to_le_bytes());
+    let t = translation.map(|[x, y, z]| format!("{x} {y} {z}"));
+    match &t {
+        Some(t) => bytes.extend(dict(&[("_t", t)])),
+        None => bytes.extend(dict(&[])),
+    }
+    bytes
+}
+
+fn dict(entries: &[(&str, &str)]) -> Vec<u8> {
+    let mut bytes = (entries.len() as i32).to_le_bytes().to_vec();
+    for (key, value) in entries {
+        for s in [key, value] {
+            bytes.extend((s.len() as i32).to_le_bytes());
+            bytes.extend(s.as_bytes());
+        }
+    }
+    bytes
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).context("unexpected end of file")?;
+        let bytes = self
+            .bytes
+            .get(self.pos..end)
+            .context("unexpected end of file")?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    /// A length or count, which must not be negative.
+    fn len(&mut self) -> anyhow::Result<usize> {
+        let n = self.i32()?;
+        usize::try_from(n)
+            .ok()
+            .with_context(|| format!("negative length {n}"))
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.len()?;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> anyhow::Result<HashMap<String, String>> {
+        let n = self.i32()?;
+        (0..n)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+}